@@ -0,0 +1,17 @@
+# Change the shell's working directory to the last directory fm was in.
+# Copy this file to ~/.config/fish/functions/fmcd.fish and use fmcd
+# instead of fm. You can also bind it to a key, for example ctrl-o:
+#
+#     bind \co 'fmcd; commandline -f repaint'
+
+function fmcd --wraps fm --description 'fm and cd to the last directory on exit'
+    set -l tmp (mktemp)
+    fm --last-dir-path=$tmp $argv
+    if test -f $tmp
+        set -l dir (cat $tmp)
+        rm -f $tmp
+        if test -d "$dir"; and test "$dir" != (pwd)
+            cd $dir
+        end
+    end
+end
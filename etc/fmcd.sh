@@ -0,0 +1,25 @@
+# Change the shell's working directory to the last directory fm was in.
+# Works in bash and zsh, source it from your ~/.bashrc or ~/.zshrc:
+#
+#     source /path/to/fmcd.sh
+#
+# Then use fmcd instead of fm. You can also bind it to a key,
+# for example ctrl-o in bash:
+#
+#     bind '"\C-o":"fmcd\C-m"'
+#
+# or in zsh:
+#
+#     bindkey -s '^o' 'fmcd\n'
+
+fmcd () {
+    tmp="$(mktemp)"
+    fm --last-dir-path="$tmp" "$@"
+    if [ -f "$tmp" ]; then
+        dir="$(cat "$tmp")"
+        rm -f "$tmp"
+        if [ -d "$dir" ] && [ "$dir" != "$(pwd)" ]; then
+            cd "$dir" || return
+        fi
+    fi
+}
@@ -1,48 +1,153 @@
 // std Imports
 use std::sync::mpsc;
+use std::process::{Command, ExitStatus};
+use std::io::{IsTerminal, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 use fm::userinput::{
-    Input, Search, FileDelete,
-    FileCreate, FileRename, FileCopy
+    Input, Search, FileDelete, Chmod, FileChown,
+    FileCreate, FileRename, FileCopy,
+    CommandLine, GlobSelect,
 };
 // Lib Imports
 use fm::filetype::FileType;
-use fm::{app::{App, InputMode}, ui};
+use fm::{app::{App, InputMode}, ui, Action, Config};
+use fm::plugin::{self, Plugins};
+use fm::args::Args;
+use fm::remote::{self, Request};
+use fm::watch::Watcher;
+use fm::sort::Sort;
 
 // Crossterm Imports
 use crossterm::{
     execute, 
     terminal::{ EnterAlternateScreen, LeaveAlternateScreen, enable_raw_mode, disable_raw_mode},
-    event::{read, poll, Event as CEvent, KeyEvent, KeyCode},
+    event::{
+        read, poll, Event as CEvent, KeyEvent, KeyCode, MouseEvent, MouseEventKind,
+        MouseButton, EnableMouseCapture, DisableMouseCapture,
+    },
 };
 
+use anyhow::Context;
+
 // Tui imports
-use tui::{Terminal, backend::CrosstermBackend};
+use tui::{Terminal, backend::CrosstermBackend, layout::Rect};
+
+// Second click on the same file within this opens it
+const DOUBLE_CLICK: Duration = Duration::from_millis(500);
+// Files or lines scrolled by one turn of the mouse wheel
+const WHEEL_LINES: isize = 3;
+
+// Terminal that fm draws to
+type Term = Terminal<CrosstermBackend<Box<dyn Write>>>;
 
 // Handles wether input is recieved
 enum Event<I>{
     Input(I),
+    Mouse(MouseEvent),
     Tick,
+    Remote(Request),
+    Refresh,
+    Resize,
 }
 
-fn main() -> anyhow::Result<()> {
+fn main() {
+    if let Err(e) = run() {
+        // Put the terminal back so the error can be read
+        let _ = disable_raw_mode();
+        if std::io::stdout().is_terminal() {
+            let _ = execute!(std::io::stdout(), DisableMouseCapture, LeaveAlternateScreen);
+        }
+        eprintln!("fm: {:#}", e);
+        std::process::exit(1)
+    }
+}
+
+fn run() -> anyhow::Result<()> {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("fm: {}\nTry 'fm --help' for more information", e);
+            std::process::exit(2)
+        }
+    };
+
+    if args.help {
+        println!("{}", fm::args::USAGE);
+        return Ok(())
+    }
+
+    if args.version {
+        println!("fm {}", env!("CARGO_PKG_VERSION"));
+        return Ok(())
+    }
+
+    if let Some(command) = &args.remote {
+        let (pid, command) = remote::client_command(command, &std::env::current_dir()?);
+        print!("{}", remote::send(pid, &command)?);
+        return Ok(())
+    }
+
+    let mut app = match App::open(args.path.as_deref(), args.config.as_deref()) {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("fm: {:#}", e);
+            std::process::exit(1)
+        }
+    };
+    app.choose = args.chooser().map(|(choose, _)| choose);
+
+    // Plugins live next to the config file
+    let plugins = match args.config.clone().or_else(Config::default_path) {
+        Some(config) => Plugins::load(&plugin::dir(&config), &mut app),
+        None => Plugins::new(),
+    };
     
     // Enable Raw Mode
-    enable_raw_mode()?;
+    enable_raw_mode().context("Can't set up the terminal")?;
 
     // Create channel for communicating across threads
     let (tx, rx) = mpsc::channel();
-    let (tx1, rx1) = mpsc::channel();
+
+    // Listen for commands from `fm -remote`, they're handled
+    // by the render loop like any other event
+    let remote_tx = tx.clone();
+    let socket = match remote::listen(move |req| remote_tx.send(Event::Remote(req)).is_ok()) {
+        Ok(socket) => Some(socket),
+        Err(e) => {
+            app.err(&format!("{:#}", e));
+            None
+        }
+    };
+
+    // Refresh the listing when something changes on disk
+    let refresh_tx = tx.clone();
+    let mut watcher = match Watcher::new(move || refresh_tx.send(Event::Refresh).is_ok()) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            app.err(&format!("Can't watch for changes: {}", e));
+            None
+        }
+    };
 
     // Creates the input handling thread
-    handle_input(tx, rx1);
+    let input = InputThread::spawn(tx);
+
+    // Draw to the tty when stdout is redirected, 
+    // e.g. `cd "$(fm --print-last-dir)"`
+    let mut out: Box<dyn Write> = if std::io::stdout().is_terminal() {
+        Box::new(std::io::stdout())
+    } else {
+        Box::new(std::fs::OpenOptions::new().write(true).open("/dev/tty").context("Can't open /dev/tty")?)
+    };
 
     // Create Alternate Screen
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(out, EnterAlternateScreen, EnableMouseCapture)?;
 
     // Create a crossterm backend and create a terminal to draw to
-    let backend = CrosstermBackend::new(stdout);
+    let backend = CrosstermBackend::new(out);
     let mut terminal = Terminal::new(backend)?;
 
     // Set panic behavior
@@ -52,155 +157,75 @@ fn main() -> anyhow::Result<()> {
         default_panic(info);
     }));
 
-    render_loop(&mut terminal, rx, tx1)?;
+    let result = render_loop(&mut terminal, &mut app, &plugins, &mut watcher, rx, &input);
+    if let Some(socket) = socket {
+        let _ = std::fs::remove_file(socket);
+    }
+    result?;
+
+    // Let the shell know where we ended up
+    let last_dir = app.wd.cwd();
+    if let Some(path) = &args.last_dir_path {
+        std::fs::write(path, last_dir.as_os_str().as_bytes())?;
+    }
+    if args.print_last_dir {
+        println!("{}", last_dir.display());
+    }
+
+    // Hand the chosen paths to whoever started us, quitting
+    // without choosing anything counts as cancelling
+    if let Some((_, path)) = args.chooser() {
+        if app.chosen.is_empty() {
+            std::process::exit(1)
+        }
+
+        let mut chosen = app.chosen
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        chosen.push('\n');
+
+        if path == Path::new("-") {
+            std::io::stdout().write_all(chosen.as_bytes())?;
+        } else {
+            std::fs::write(path, chosen)?;
+        }
+    }
 
     Ok(())
 }
 
 fn render_loop(
-    terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, 
+    terminal: &mut Term, 
+    app: &mut App,
+    plugins: &Plugins,
+    watcher: &mut Option<Watcher>,
     rx: mpsc::Receiver<Event<KeyEvent>>,
-    tx1: mpsc::Sender<()>,
+    input: &InputThread,
     ) -> anyhow::Result<()> 
 {
     terminal.hide_cursor()?;
-    let mut app = App::new();
     let mut user_inp: Box<dyn Input> = Box::new(Search::default());
+    let mut last_cwd = app.wd.cwd().to_owned();
+    let mut last_click = None;
 
     // let mut user_inp = Search::default();
 
     loop {
-        terminal.draw(|rect| ui::draw(rect, &mut app, &mut user_inp))?;
+        plugins.update_preview(app);
+        terminal.draw(|rect| ui::draw(rect, app, &mut user_inp))?;
 
         // Handle input send from other thread
         match rx.recv()? {
+            // Any key closes the help and file info
+            Event::Input(_) if app.show_help => app.show_help = false,
+            Event::Input(_) if app.show_info => app.show_info = false,
             Event::Input(event) => match app.input_mode {
-                InputMode::Normal => match event.code {
-                    KeyCode::Char('q') => {
-                    // Call shutdown method
-                    shutdown(terminal.backend_mut())?;
-                    break;
-                    },
-                    // Goes down the list and wraps up to the top
-                    KeyCode::Char('j') => {
-                        if let Some(selected) = app.flist_state.selected() {
-                            let num_files = app.displayed_files.len();
-                            if selected >= num_files -1 {
-                                app.flist_state.select(Some(0))
-                            } else {
-                                app.flist_state.select(Some(selected + 1))
-                            }
-                        }
-                    },
-                    // Goes up the list
-                    KeyCode::Char('k') => {
-                        if let Some(selected) = app.flist_state.selected() {
-                            if selected > 0 {
-                                app.flist_state.select(Some(selected - 1))
-                            } else {
-                                app.flist_state.select(Some(0))
-                            }
-                        } 
-                    },
-                    // Going back
-                    KeyCode::Char('h') => {
-                        app.wd_back();
-                        user_inp.clear();
-                    },
-                    // Going forward
-                    KeyCode::Char('l') => {
-                        // Checks to see if the directory is valid
-                        if app.selected_file().is_some()
-                        && app.selected_file().unwrap().ftype == FileType::Directory
-                        && std::fs::read_dir(app.selected_file().unwrap().path()).is_ok() {
-                            app.wd_forward();
-                            user_inp.clear();
-                        }                    
-                    },
-                    KeyCode::Enter => {
-                        if let Some(selected_file) = app.selected_file() {
-                            if selected_file.ftype == FileType::File {
-                                tx1.send(())?;
-                                execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-                                std::process::Command::new("nvim")
-                                    .arg(selected_file.path())
-                                    .spawn()
-                                    .unwrap()
-                                    .wait()
-                                    .unwrap();
-                                execute!(terminal.backend_mut(), EnterAlternateScreen)?;
-                                terminal.clear()?;
-                                tx1.send(())?;
-                            }
-                        }
-                        app.selected_file_mut().unwrap().update_size();
-                    },
-                    KeyCode::Char('g') => {
-                        if &app.key_press == "g" {
-                            app.flist_state.select(Some(0));
-                            app.key_press.clear()
-                        } else {
-                            app.key_press = "g".to_string();
-                        }
-                    },
-                    // Keymap to jump to the last element
-                    KeyCode::Char('G') => {
-                        let num_files = app.wd.files().len();
-                        app.flist_state.select(Some(num_files - 1))
-                    },
-                    KeyCode::Char('d') => { 
-                        app.to_editing_mode();
-                        user_inp = Box::new(FileDelete::default());
-                    },
-                    KeyCode::Char('a')=> {
-                        app.to_editing_mode();
-                        user_inp = Box::new(FileCreate::default())
+                InputMode::Normal => {
+                    if let Some((actions, count)) = app.keys.feed(&event, app.input_mode, &app.config) {
+                        actions.into_iter().for_each(|a| app.queue_count(a, count))
                     }
-                    KeyCode::Char('A') => {
-                        app.to_editing_mode();
-                        user_inp = Box::new(FileCreate::default().dir())
-                    }
-                    KeyCode::Char('r') => {
-                        app.to_editing_mode();
-                        if let Some(file) = app.selected_file() {
-                            user_inp = Box::new(FileRename::default().file(file))
-                        }
-                    }
-                    KeyCode::Char('p') => {
-                        println!("name: {} path: {:?}", app.selected_file().unwrap().name, app.selected_file().unwrap().path())
-                    }
-                    KeyCode::Char('/') => { 
-                        if app.is_searching {
-                            app.input_mode = InputMode::Editing
-                        } else {
-                            user_inp = Box::new(Search::default());
-                            app.input_mode = InputMode::Editing;
-                            app.is_searching = true;
-                        }
-                    },
-
-                    KeyCode::Char('c') => {
-                        app.to_editing_mode();
-                        if let Some(file) = app.selected_file() {
-                            user_inp = Box::new(FileCopy::default().file(file));
-                        }
-                    }
-
-                    KeyCode::Char('v') => {
-                        if let Some(file) = app.selected_file_mut() {
-                            file.is_selected = !file.is_selected;
-                        }
-                    },
-
-                    KeyCode::Esc => {
-                        if app.is_searching {
-                            app.end_input()
-                        } else {
-                            app.clear_selection();
-                            app.to_normal_mode()
-                        }
-                    }
-                    _ => {}
                 },
                 InputMode::Editing => match event.code {
                     KeyCode::Esc => { 
@@ -210,51 +235,478 @@ fn render_loop(
                         app.is_searching = false;
                     },
                     KeyCode::Enter => { 
-                        user_inp.on_enter(&mut app)
+                        user_inp.on_enter(app)
+                    }
+                    KeyCode::Tab => {
+                        user_inp.complete(app)
                     }
                     KeyCode::Char(c) => {
-                        user_inp.add_to_input(c, &mut app)
+                        user_inp.add_to_input(c, app)
                     }, 
                     KeyCode::Backspace => {
-                        user_inp.del(&mut app);
+                        user_inp.del(app);
                     }
-                    _ => {} 
+                    code => user_inp.on_key(code, app),
                 }
-                InputMode::Visual => match event.code {
-                    _ => {}
+                // Moving extends the range and operators apply to it
+                InputMode::Visual => {
+                    if let Some((actions, count)) = app.keys.feed(&event, app.input_mode, &app.config) {
+                        for action in actions {
+                            match action {
+                                Action::Down | Action::Up | Action::Top | Action::Bottom
+                                | Action::HalfPageDown | Action::HalfPageUp | Action::PageDown
+                                | Action::PageUp | Action::ScreenTop | Action::ScreenMiddle
+                                | Action::ScreenBottom => {
+                                    app.queue_count(action, count)
+                                },
+                                Action::Visual | Action::Delete | Action::Yank | Action::Cut | Action::Copy
+                                | Action::Move | Action::Chmod | Action::Chown => {
+                                    app.queue(action)
+                                },
+                                Action::ClearSelection => app.end_visual(false),
+                                _ => {}
+                            }
+                        }
+                    }
                 },
                 InputMode::Error => { app.to_normal_mode() }
             },
-            Event::Tick => {}
+            Event::Remote(req) => {
+                let reply = match req.command.parse::<remote::Command>() {
+                    Ok(cmd) => cmd.run(app),
+                    Err(e) => Err(e),
+                };
+                let _ = req.reply.send(reply);
+            },
+            Event::Refresh => {
+                app.preview = None;
+                app.wd.forget_gitignores();
+                app.reload();
+            },
+            Event::Mouse(_) if app.show_help || app.show_info => {},
+            Event::Mouse(mouse) => match app.input_mode {
+                InputMode::Normal | InputMode::Visual => {
+                    handle_mouse(mouse, app, terminal.size()?, &mut last_click)
+                },
+                _ => {}
+            },
+            // Drawing picks up the new size
+            Event::Resize | Event::Tick => {}
+        }
+
+        // Run everything queued by keybinds, commands, inputs and plugins
+        loop {
+            while let Some((action, count)) = app.actions.pop_front() {
+                if !run_action(action, count, app, &mut user_inp, plugins, terminal, input)? {
+                    // Call shutdown method
+                    shutdown(terminal.backend_mut())?;
+                    return Ok(())
+                }
+            }
+
+            // Plugin hooks may queue more actions when the directory changes
+            if app.wd.cwd() == &last_cwd {
+                break
+            }
+            last_cwd = app.wd.cwd().to_owned();
+            plugins.emit("cd", &last_cwd.display().to_string(), app);
         }
+
+        // Watch the cwd and the directory being previewed
+        if let Some(watcher) = watcher {
+            let mut dirs = vec![app.wd.cwd().clone()];
+            dirs.extend(app.other_dir().map(Path::to_owned));
+            dirs.extend(app.expanded_dirs());
+            if let Some(file) = app.selected_file() {
+                if file.ftype == FileType::Directory {
+                    dirs.push(file.path().to_owned())
+                }
+            }
+            watcher.watch(&dirs);
+        }
+    }
+}
+
+/// Clicking a file moves the cursor to it and clicking it again opens it,
+/// clicking a directory in the header goes to it, clicking the other pane
+/// switches to it and the wheel scrolls the list or the preview
+fn handle_mouse(mouse: MouseEvent, app: &mut App, size: Rect, last_click: &mut Option<(Instant, usize)>) {
+    // Nothing is drawn where the mouse could point to
+    if ui::too_small(size) {
+        return
+    }
+
+    let (column, row) = (mouse.column, mouse.row);
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(i) = ui::list_index_at(app, size, column, row) {
+                let double = matches!(*last_click, Some((at, j)) if j == i && at.elapsed() < DOUBLE_CLICK);
+                app.flist_state.select(Some(i));
+                *last_click = Some((Instant::now(), i));
+
+                if double {
+                    *last_click = None;
+                    match app.selected_file().map(|f| f.ftype == FileType::Directory) {
+                        Some(true) => app.queue(Action::Forward),
+                        _ => app.queue(Action::Open),
+                    }
+                }
+            } else if ui::in_other_pane(app, size, column, row) {
+                app.queue(Action::SwitchPane)
+            } else if let Some(dir) = ui::cwd_ancestor_at(app.wd.cwd(), size, column, row) {
+                if let Err(e) = app.cd(&dir) {
+                    app.err(&format!("{:#}", e))
+                }
+            }
+        },
+        MouseEventKind::ScrollDown if ui::in_preview(app, size, column, row) => app.scroll_preview(WHEEL_LINES, ui::preview_lines(app)),
+        MouseEventKind::ScrollUp if ui::in_preview(app, size, column, row) => app.scroll_preview(-WHEEL_LINES, ui::preview_lines(app)),
+        MouseEventKind::ScrollDown => app.scroll_by(WHEEL_LINES),
+        MouseEventKind::ScrollUp => app.scroll_by(-WHEEL_LINES),
+        _ => {}
+    }
+}
+
+/// Runs a single action, returns false if fm should quit. Motions
+/// and operators take the count, other actions ignore it
+fn run_action(
+    action: Action,
+    count: Option<usize>,
+    app: &mut App,
+    user_inp: &mut Box<dyn Input>,
+    plugins: &Plugins,
+    terminal: &mut Term, 
+    input: &InputThread,
+    ) -> anyhow::Result<bool>
+{
+    match action {
+        Action::Quit => return Ok(false),
+        Action::Down => match count {
+            Some(count) => app.move_down_by(count),
+            None => app.move_down(),
+        },
+        Action::Up => match count {
+            Some(count) => app.move_up_by(count),
+            None => app.move_up(),
+        },
+        // With a count both jump to that line
+        Action::Top | Action::Bottom if count.is_some() => app.move_to(count.unwrap()),
+        Action::Top => app.move_top(),
+        Action::Bottom => app.move_bottom(),
+        // Like vim a count sets how far half pages scroll
+        Action::HalfPageDown => app.scroll_by(count.unwrap_or(app.list_height / 2).max(1) as isize),
+        Action::HalfPageUp => app.scroll_by(-(count.unwrap_or(app.list_height / 2).max(1) as isize)),
+        Action::PageDown => app.scroll_by((count.unwrap_or(1) * app.list_height) as isize),
+        Action::PageUp => app.scroll_by(-((count.unwrap_or(1) * app.list_height) as isize)),
+        Action::ScreenTop => app.move_screen_top(count.unwrap_or(1)),
+        Action::ScreenMiddle => app.move_screen_middle(),
+        Action::ScreenBottom => app.move_screen_bottom(count.unwrap_or(1)),
+        // Going back
+        Action::Back => {
+            app.wd_back();
+            user_inp.clear();
+        },
+        // Going forward
+        Action::Forward => {
+            // Checks to see if the directory is valid
+            if app.selected_file().is_some()
+            && app.selected_file().unwrap().ftype == FileType::Directory
+            && std::fs::read_dir(app.selected_file().unwrap().path()).is_ok() {
+                app.wd_forward();
+                user_inp.clear();
+            }                    
+        },
+        // When choosing files enter picks instead of opening
+        Action::Open if app.choose.is_some() => {
+            let choices = app.choices();
+            if choices.is_empty() {
+                app.queue(Action::Forward)
+            } else {
+                app.chosen = choices;
+                return Ok(false)
+            }
+        },
+        Action::Open => {
+            if let Some(selected_file) = app.selected_file().cloned() {
+                if selected_file.ftype == FileType::File {
+                    plugins.emit("open", &selected_file.path().display().to_string(), app);
+                    let mut cmd = Command::new("nvim");
+                    cmd.arg(selected_file.path());
+                    if let Err(e) = run_external(terminal, input, &mut cmd) {
+                        app.err(&e.to_string())
+                    }
+                }
+            }
+            if let Some(file) = app.selected_file_mut() {
+                file.update_size();
+            }
+        },
+        Action::Delete => { 
+            let files = app.operands(count);
+            app.end_visual(false);
+            if !files.is_empty() {
+                app.to_editing_mode();
+                *user_inp = Box::new(FileDelete::default().files(files));
+            }
+        },
+        Action::Chmod => {
+            let files = app.operands(count);
+            app.end_visual(false);
+            if !files.is_empty() {
+                app.to_editing_mode();
+                *user_inp = Box::new(Chmod::default().files(files));
+            }
+        },
+        Action::Chown => {
+            let files = app.operands(count);
+            app.end_visual(false);
+            if !files.is_empty() {
+                app.to_editing_mode();
+                *user_inp = Box::new(FileChown::default().files(files));
+            }
+        },
+        Action::Create => {
+            app.to_editing_mode();
+            *user_inp = Box::new(FileCreate::default())
+        }
+        Action::CreateDir => {
+            app.to_editing_mode();
+            *user_inp = Box::new(FileCreate::default().dir())
+        }
+        Action::Rename => {
+            app.to_editing_mode();
+            if let Some(file) = app.selected_file() {
+                *user_inp = Box::new(FileRename::default().file(file));
+            }
+        }
+        // With two panes files go to the other one
+        Action::Copy if app.other_dir().is_some() => app.to_other_pane(false, count),
+        Action::Copy => {
+            app.to_editing_mode();
+            if let Some(file) = app.selected_file() {
+                *user_inp = Box::new(FileCopy::default().file(file));
+            }
+        }
+        Action::Move => app.to_other_pane(true, count),
+        Action::Search => { 
+            if app.is_searching {
+                app.input_mode = InputMode::Editing
+            } else {
+                *user_inp = Box::new(Search::default());
+                app.input_mode = InputMode::Editing;
+                app.is_searching = true;
+            }
+        },
+        Action::Select => {
+            if let Some(path) = app.selected_file().map(|f| f.path.clone()) {
+                app.toggle_selection(&path);
+            }
+        },
+        Action::ClearSelection => {
+            if app.is_searching {
+                app.end_input()
+            } else {
+                app.clear_selection();
+                app.to_normal_mode()
+            }
+        }
+        Action::Visual => {
+            if app.input_mode == InputMode::Visual {
+                app.end_visual(true)
+            } else {
+                app.start_visual()
+            }
+        },
+        Action::InvertSelection => app.invert_selection(),
+        Action::SelectAll => app.select_all(),
+        Action::UnselectAll => app.clear_selection(),
+        Action::GlobSelect => {
+            app.to_editing_mode();
+            *user_inp = Box::new(GlobSelect::default());
+        },
+        Action::GlobUnselect => {
+            app.to_editing_mode();
+            *user_inp = Box::new(GlobSelect::default().unselect());
+        },
+        Action::Yank => app.yank(false, count),
+        Action::Cut => app.yank(true, count),
+        Action::Paste => app.paste(),
+        // Each tab has its own search
+        Action::NewTab | Action::CloseTab | Action::NextTab | Action::PrevTab => {
+            let tabs = app.tab_count();
+            match action {
+                Action::NewTab => app.new_tab(),
+                Action::CloseTab => app.close_tab(),
+                // Like vim a count goes to that tab
+                Action::NextTab => app.switch_tab(match count {
+                    Some(n) => n.saturating_sub(1),
+                    None => (app.tab + 1) % tabs,
+                }),
+                _ => app.switch_tab((app.tab + tabs - count.unwrap_or(1) % tabs) % tabs),
+            }
+            user_inp.clear();
+        },
+        Action::HistoryBack => {
+            app.history_back();
+            user_inp.clear();
+        },
+        Action::DualPane => app.toggle_dual_pane(),
+        Action::LongListing => app.toggle_long(),
+        Action::Info => app.show_info = app.selected_file().is_some(),
+        Action::Tree => app.toggle_tree(),
+        Action::Expand => app.expand(),
+        Action::Collapse => app.collapse(),
+        Action::Sort(by) => app.set_sort(Sort { by, ..app.wd.sort() }),
+        Action::SortReverse => app.set_sort(Sort { reverse: !app.wd.sort().reverse, ..app.wd.sort() }),
+        Action::SortDirsFirst => app.set_sort(Sort { dirs_first: !app.wd.sort().dirs_first, ..app.wd.sort() }),
+        Action::ToggleHidden => app.toggle_hidden(),
+        Action::ToggleGitignore => app.toggle_gitignore(),
+        Action::SwitchPane => {
+            app.switch_pane();
+            user_inp.clear();
+        },
+        // Rereads .gitignore files above the cwd too, they aren't watched
+        Action::Reload => {
+            app.wd.forget_gitignores();
+            app.reload()
+        },
+        Action::Help => app.show_help = true,
+        Action::CommandLine => {
+            app.to_editing_mode();
+            *user_inp = Box::new(CommandLine::default());
+        }
+        Action::Shell(cmd) => run_shell(&cmd, app, terminal, input)?,
+        Action::Plugin(name) => plugins.run_command(&name, app),
+    }
+
+    Ok(true)
+}
+
+/// Runs a snippet with `sh -c` in the current directory. Like lf the
+/// current file is passed in `$f`, the selected files in `$fs` and
+/// the selected files or the current file if none are selected in `$fx`
+fn run_shell(
+    snippet: &str,
+    app: &mut App,
+    terminal: &mut Term, 
+    input: &InputThread,
+    ) -> anyhow::Result<()>
+{
+    let current = app.selected_file()
+        .map(|f| f.path().display().to_string())
+        .unwrap_or_default();
+
+    let selected = app.selection
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let fx = if selected.is_empty() { current.clone() } else { selected.clone() };
+
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg(snippet)
+        .current_dir(app.wd.cwd())
+        .env("f", current)
+        .env("fs", selected)
+        .env("fx", fx);
+
+    let status = run_external(terminal, input, &mut cmd);
+
+    // The command may have changed the directory
+    app.reload();
+    match status {
+        Ok(status) if status.success() => {},
+        Ok(status) => app.err(&format!("Command {}", status)),
+        Err(e) => app.err(&e.to_string()),
     }
 
     Ok(())
 }
 
+/// Hands the terminal over to a child process until it exits
+fn run_external(
+    terminal: &mut Term, 
+    input: &InputThread,
+    cmd: &mut Command,
+    ) -> anyhow::Result<ExitStatus>
+{
+    // Lets the command find us with `fm --remote`
+    cmd.env("FM_PID", std::process::id().to_string());
 
-// Input Handling Thread
-// Takes a transmitter and a tickrate and listens for input
-fn handle_input(tx: mpsc::Sender<Event<KeyEvent>>, rx: mpsc::Receiver<()>) {
-    use std::thread;
-    use std::time::{Duration, Instant};
+    suspend(terminal, input)?;
+    let status = cmd.status();
+    resume(terminal, input)?;
 
-    let tick_rate = Duration::from_millis(200);
+    Ok(status?)
+}
 
-    thread::spawn(move || -> anyhow::Result<()> {
-        let mut last_tick = Instant::now();
+/// Gives the terminal back to the shell so another program can use it,
+/// the input thread stops reading so it doesn't steal the program's input
+fn suspend(terminal: &mut Term, input: &InputThread) -> anyhow::Result<()> {
+    input.pause()?;
+    execute!(terminal.backend_mut(), DisableMouseCapture, LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+    terminal.show_cursor()?;
+    Ok(())
+}
 
-        loop {
-            if rx.recv_timeout(Duration::from_millis(10)).is_err() {
-                // Time before we want to time out
+/// Takes the terminal back after suspend and redraws everything
+fn resume(terminal: &mut Term, input: &InputThread) -> anyhow::Result<()> {
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.hide_cursor()?;
+    terminal.clear()?;
+    input.resume()
+}
+
+// Messages to the input thread
+enum Control {
+    Pause,
+    Resume,
+}
+
+/// The thread reading terminal events and sending them to the render
+/// loop, along with a tick every 200ms
+struct InputThread {
+    control: mpsc::Sender<Control>,
+    paused: mpsc::Receiver<()>,
+}
+
+impl InputThread {
+    fn spawn(tx: mpsc::Sender<Event<KeyEvent>>) -> Self {
+        let (control, control_rx) = mpsc::channel();
+        let (paused_tx, paused) = mpsc::channel();
+
+        std::thread::spawn(move || -> anyhow::Result<()> {
+            let tick_rate = Duration::from_millis(200);
+            let mut last_tick = Instant::now();
+
+            loop {
+                match control_rx.try_recv() {
+                    Ok(Control::Pause) => {
+                        paused_tx.send(())?;
+                        // Nothing is read until we're told to resume
+                        while let Control::Pause = control_rx.recv()? {
+                            paused_tx.send(())?
+                        }
+                        last_tick = Instant::now();
+                    },
+                    Ok(Control::Resume) | Err(mpsc::TryRecvError::Empty) => {},
+                    Err(mpsc::TryRecvError::Disconnected) => return Ok(()),
+                }
+
+                // Wait in short steps so pausing doesn't take long
                 let timeout = tick_rate
-                    .checked_sub(last_tick.elapsed())
-                    .unwrap_or_else(|| Duration::from_secs(0));
+                    .saturating_sub(last_tick.elapsed())
+                    .min(Duration::from_millis(20));
 
                 // If an event is available, send it to the rendering thread 
                 if poll(timeout)? {
-                    if let CEvent::Key(key) = read()? {
-                        tx.send(Event::Input(key))?
+                    match read()? {
+                        CEvent::Key(key) => tx.send(Event::Input(key))?,
+                        CEvent::Mouse(mouse) => tx.send(Event::Mouse(mouse))?,
+                        CEvent::Resize(..) => tx.send(Event::Resize)?,
                     }
                 }
 
@@ -263,16 +715,28 @@ fn handle_input(tx: mpsc::Sender<Event<KeyEvent>>, rx: mpsc::Receiver<()>) {
                 if last_tick.elapsed() >= tick_rate && tx.send(Event::Tick).is_ok() {
                     last_tick = Instant::now()
                 } 
-
-            } else {
-                while rx.recv_timeout(tick_rate).is_err() {}
             }
-        }
-    });
+        });
+
+        Self { control, paused }
+    }
+
+    /// Stops reading events, returns once the thread has stopped
+    fn pause(&self) -> anyhow::Result<()> {
+        self.control.send(Control::Pause)?;
+        self.paused.recv()?;
+        Ok(())
+    }
+
+    /// Starts reading events again
+    fn resume(&self) -> anyhow::Result<()> {
+        self.control.send(Control::Resume)?;
+        Ok(())
+    }
 }
 
-fn shutdown(backend: &mut CrosstermBackend<std::io::Stdout>) -> anyhow::Result<()> {
-    execute!(backend, LeaveAlternateScreen)?;
+fn shutdown(backend: &mut CrosstermBackend<Box<dyn Write>>) -> anyhow::Result<()> {
+    execute!(backend, DisableMouseCapture, LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
 }
@@ -0,0 +1,412 @@
+use std::str::FromStr;
+
+use super::sort::SortBy;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Deserializer};
+
+/// Everything fm knows how to do from Normal mode. Keybindings,
+/// the command line and user defined commands all resolve to Actions
+/// which are then run by the render loop
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    Down,
+    Up,
+    Top,
+    Bottom,
+    HalfPageDown,
+    HalfPageUp,
+    PageDown,
+    PageUp,
+    ScreenTop,
+    ScreenMiddle,
+    ScreenBottom,
+    Back,
+    Forward,
+    Open,
+    Delete,
+    Create,
+    CreateDir,
+    Rename,
+    Chmod,
+    Chown,
+    Copy,
+    Move,
+    Search,
+    Select,
+    ClearSelection,
+    Visual,
+    InvertSelection,
+    SelectAll,
+    UnselectAll,
+    GlobSelect,
+    GlobUnselect,
+    Yank,
+    Cut,
+    Paste,
+    NewTab,
+    CloseTab,
+    NextTab,
+    PrevTab,
+    HistoryBack,
+    DualPane,
+    SwitchPane,
+    LongListing,
+    Info,
+    Tree,
+    Expand,
+    Collapse,
+    /// Sort every listing by something else
+    Sort(SortBy),
+    SortReverse,
+    SortDirsFirst,
+    ToggleHidden,
+    ToggleGitignore,
+    Reload,
+    Help,
+    CommandLine,
+    /// Run a snippet with `sh -c`
+    Shell(String),
+    /// Run a command registered by a plugin
+    Plugin(String),
+}
+
+impl Action {
+    /// Every builtin action, in the order they are listed in the help
+    pub fn builtins() -> Vec<Action> {
+        use Action::*;
+        let mut builtins = vec![
+            Quit, Down, Up, Top, Bottom, HalfPageDown, HalfPageUp, PageDown,
+            PageUp, ScreenTop, ScreenMiddle, ScreenBottom, Back, Forward, Open, Delete, Create,
+            CreateDir, Rename, Chmod, Chown, Copy, Move, Search, Select, ClearSelection, Visual,
+            InvertSelection, SelectAll, UnselectAll, GlobSelect, GlobUnselect,
+            Yank, Cut, Paste, NewTab, CloseTab, NextTab, PrevTab, HistoryBack,
+            DualPane, SwitchPane, LongListing, Info, Tree, Expand, Collapse,
+        ];
+        builtins.extend(SortBy::all().into_iter().map(Sort));
+        builtins.extend([
+            SortReverse, SortDirsFirst, ToggleHidden, ToggleGitignore, Reload, Help, CommandLine,
+        ]);
+        builtins
+    }
+
+    /// Maps a key pressed in Normal mode to its default action
+    pub fn from_key(key: &KeyEvent) -> Option<Self> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) {
+            let action = match key.code {
+                KeyCode::Char('d') => Action::HalfPageDown,
+                KeyCode::Char('u') => Action::HalfPageUp,
+                KeyCode::Char('f') => Action::PageDown,
+                KeyCode::Char('b') => Action::PageUp,
+                KeyCode::Char('o') => Action::HistoryBack,
+                KeyCode::Char('w') => Action::DualPane,
+                _ => return None,
+            };
+            return Some(action)
+        }
+
+        let action = match key.code {
+            KeyCode::Char('q') => Action::Quit,
+            KeyCode::Char('j') => Action::Down,
+            KeyCode::Char('k') => Action::Up,
+            KeyCode::Char('G') => Action::Bottom,
+            KeyCode::Char('H') => Action::ScreenTop,
+            KeyCode::Char('M') => Action::ScreenMiddle,
+            KeyCode::Char('L') => Action::ScreenBottom,
+            KeyCode::Char('h') => Action::Back,
+            KeyCode::Char('l') => Action::Forward,
+            KeyCode::Enter => Action::Open,
+            KeyCode::Tab => Action::SwitchPane,
+            KeyCode::Char('d') => Action::Delete,
+            KeyCode::Char('a') => Action::Create,
+            KeyCode::Char('A') => Action::CreateDir,
+            KeyCode::Char('r') => Action::Rename,
+            KeyCode::Char('=') => Action::Chmod,
+            KeyCode::Char('O') => Action::Chown,
+            KeyCode::Char('c') => Action::Copy,
+            KeyCode::Char('m') => Action::Move,
+            KeyCode::Char('/') => Action::Search,
+            KeyCode::Char('v') => Action::Select,
+            KeyCode::Esc => Action::ClearSelection,
+            KeyCode::Char('V') => Action::Visual,
+            KeyCode::Char('*') => Action::InvertSelection,
+            KeyCode::Char('u') => Action::UnselectAll,
+            KeyCode::Char('+') => Action::GlobSelect,
+            KeyCode::Char('-') => Action::GlobUnselect,
+            KeyCode::Char('y') => Action::Yank,
+            KeyCode::Char('x') => Action::Cut,
+            KeyCode::Char('p') => Action::Paste,
+            KeyCode::Char('i') => Action::Info,
+            KeyCode::Char('R') => Action::Reload,
+            KeyCode::Char('?') => Action::Help,
+            KeyCode::Char(':') => Action::CommandLine,
+            _ => return None,
+        };
+
+        Some(action)
+    }
+
+    /// The default key for the action as shown in the help
+    pub fn key_hint(&self) -> &'static str {
+        match self {
+            Action::Quit => "q",
+            Action::Down => "j",
+            Action::Up => "k",
+            Action::Top => "gg",
+            Action::Bottom => "G",
+            Action::HalfPageDown => "ctrl-d",
+            Action::HalfPageUp => "ctrl-u",
+            Action::PageDown => "ctrl-f",
+            Action::PageUp => "ctrl-b",
+            Action::ScreenTop => "H",
+            Action::ScreenMiddle => "M",
+            Action::ScreenBottom => "L",
+            Action::Back => "h",
+            Action::Forward => "l",
+            Action::Open => "enter",
+            Action::Delete => "dd",
+            Action::Create => "a",
+            Action::CreateDir => "A",
+            Action::Rename => "r",
+            Action::Chmod => "=",
+            Action::Chown => "O",
+            Action::Copy => "c",
+            Action::Move => "m",
+            Action::Search => "/",
+            Action::Select => "v",
+            Action::ClearSelection => "esc",
+            Action::Visual => "V",
+            Action::InvertSelection => "*",
+            Action::UnselectAll => "u",
+            Action::GlobSelect => "+",
+            Action::GlobUnselect => "-",
+            Action::Yank => "yy",
+            Action::Cut => "x",
+            Action::Paste => "p",
+            Action::NewTab => "gn",
+            Action::CloseTab => "gc",
+            Action::NextTab => "gt",
+            Action::PrevTab => "gT",
+            Action::HistoryBack => "ctrl-o",
+            Action::DualPane => "ctrl-w",
+            Action::SwitchPane => "tab",
+            Action::LongListing => "zl",
+            Action::Info => "i",
+            Action::Tree => "zt",
+            Action::Expand => "zo",
+            Action::Collapse => "zc",
+            Action::Sort(by) => match by {
+                SortBy::Natural => "on",
+                SortBy::Size => "os",
+                SortBy::Modified => "om",
+                SortBy::Created => "oc",
+                SortBy::Extension => "oe",
+                SortBy::Type => "ot",
+            },
+            Action::SortReverse => "or",
+            Action::SortDirsFirst => "od",
+            Action::ToggleHidden => "zh",
+            Action::ToggleGitignore => "zi",
+            Action::Reload => "R",
+            Action::Help => "?",
+            Action::CommandLine => ":",
+            Action::SelectAll | Action::Shell(_) | Action::Plugin(_) => "",
+        }
+    }
+
+    /// The name used for the action in the config and on the command line
+    pub fn name(&self) -> &str {
+        match self {
+            Action::Quit => "quit",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+            Action::HalfPageDown => "half-page-down",
+            Action::HalfPageUp => "half-page-up",
+            Action::PageDown => "page-down",
+            Action::PageUp => "page-up",
+            Action::ScreenTop => "screen-top",
+            Action::ScreenMiddle => "screen-middle",
+            Action::ScreenBottom => "screen-bottom",
+            Action::Back => "back",
+            Action::Forward => "forward",
+            Action::Open => "open",
+            Action::Delete => "delete",
+            Action::Create => "create",
+            Action::CreateDir => "create-dir",
+            Action::Rename => "rename",
+            Action::Chmod => "chmod",
+            Action::Chown => "chown",
+            Action::Copy => "copy",
+            Action::Move => "move",
+            Action::Search => "search",
+            Action::Select => "select",
+            Action::ClearSelection => "clear-selection",
+            Action::Visual => "visual",
+            Action::InvertSelection => "invert-selection",
+            Action::SelectAll => "select-all",
+            Action::UnselectAll => "unselect-all",
+            Action::GlobSelect => "glob-select",
+            Action::GlobUnselect => "glob-unselect",
+            Action::Yank => "yank",
+            Action::Cut => "cut",
+            Action::Paste => "paste",
+            Action::NewTab => "new-tab",
+            Action::CloseTab => "close-tab",
+            Action::NextTab => "next-tab",
+            Action::PrevTab => "prev-tab",
+            Action::HistoryBack => "history-back",
+            Action::DualPane => "dual-pane",
+            Action::SwitchPane => "switch-pane",
+            Action::LongListing => "long-listing",
+            Action::Info => "info",
+            Action::Tree => "tree",
+            Action::Expand => "expand",
+            Action::Collapse => "collapse",
+            Action::Sort(by) => match by {
+                SortBy::Natural => "sort-natural",
+                SortBy::Size => "sort-size",
+                SortBy::Modified => "sort-modified",
+                SortBy::Created => "sort-created",
+                SortBy::Extension => "sort-extension",
+                SortBy::Type => "sort-type",
+            },
+            Action::SortReverse => "sort-reverse",
+            Action::SortDirsFirst => "sort-dirs-first",
+            Action::ToggleHidden => "toggle-hidden",
+            Action::ToggleGitignore => "toggle-gitignore",
+            Action::Reload => "reload",
+            Action::Help => "help",
+            Action::CommandLine => "command",
+            Action::Shell(cmd) => cmd,
+            Action::Plugin(name) => name,
+        }
+    }
+
+    /// Short description shown in the help
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit fm",
+            Action::Down => "Move down the list",
+            Action::Up => "Move up the list",
+            Action::Top => "Jump to the first file",
+            Action::Bottom => "Jump to the last file",
+            Action::HalfPageDown => "Scroll down half a page",
+            Action::HalfPageUp => "Scroll up half a page",
+            Action::PageDown => "Scroll down a page",
+            Action::PageUp => "Scroll up a page",
+            Action::ScreenTop => "Jump to the top of the screen",
+            Action::ScreenMiddle => "Jump to the middle of the screen",
+            Action::ScreenBottom => "Jump to the bottom of the screen",
+            Action::Back => "Go to the parent directory",
+            Action::Forward => "Enter the selected directory",
+            Action::Open => "Open the selected file",
+            Action::Delete => "Delete the selected file",
+            Action::Create => "Create a new file",
+            Action::CreateDir => "Create a new directory",
+            Action::Rename => "Rename the selected file",
+            Action::Chmod => "Change the permissions of the selected files",
+            Action::Chown => "Change the owner and group of the selected files",
+            Action::Copy => "Copy the selected file, or the selected files to the other pane",
+            Action::Move => "Move the selected files to the other pane",
+            Action::Search => "Search the current directory",
+            Action::Select => "Toggle selection of the current file",
+            Action::ClearSelection => "Clear the search or selection",
+            Action::Visual => "Select a range of files",
+            Action::InvertSelection => "Invert the selection in this directory",
+            Action::SelectAll => "Select every file in this directory",
+            Action::UnselectAll => "Unselect every file in every directory",
+            Action::GlobSelect => "Select the files matching a glob",
+            Action::GlobUnselect => "Unselect the files matching a glob",
+            Action::Yank => "Copy the selected files to the clipboard",
+            Action::Cut => "Cut the selected files to the clipboard",
+            Action::Paste => "Paste the clipboard in this directory",
+            Action::NewTab => "Open a tab in this directory",
+            Action::CloseTab => "Close this tab",
+            Action::NextTab => "Go to the next tab, or tab N with a count",
+            Action::PrevTab => "Go to the previous tab",
+            Action::HistoryBack => "Go back to the last directory in this tab",
+            Action::DualPane => "Toggle two panes side by side",
+            Action::SwitchPane => "Switch to the other pane",
+            Action::LongListing => "Toggle showing permissions, owners, sizes and times",
+            Action::Info => "Show the details of the file under the cursor",
+            Action::Tree => "Toggle listing the directory as a tree",
+            Action::Expand => "Expand the directory in the tree",
+            Action::Collapse => "Collapse the directory in the tree",
+            Action::Sort(by) => match by {
+                SortBy::Natural => "Sort by name with numbers in order",
+                SortBy::Size => "Sort by size, biggest first",
+                SortBy::Modified => "Sort by modification time, newest first",
+                SortBy::Created => "Sort by creation time, newest first",
+                SortBy::Extension => "Sort by extension",
+                SortBy::Type => "Sort by type",
+            },
+            Action::SortReverse => "Toggle reversing the sort",
+            Action::SortDirsFirst => "Toggle listing directories first",
+            Action::ToggleHidden => "Toggle showing hidden files",
+            Action::ToggleGitignore => "Toggle hiding files ignored by git",
+            Action::Reload => "Reload the current directory",
+            Action::Help => "Show this help",
+            Action::CommandLine => "Run a command by name",
+            Action::Shell(_) => "Run a shell command",
+            Action::Plugin(_) => "Run a plugin command",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(cmd) = s.strip_prefix('!') {
+            return Ok(Action::Shell(cmd.trim().to_string()))
+        }
+
+        Action::builtins()
+            .into_iter()
+            .find(|a| a.name() == s)
+            .ok_or(format!("Unknown action: {}", s))
+    }
+}
+
+impl<'de> Deserialize<'de> for Action {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Action;
+
+    #[test]
+    fn test_parse_builtin() {
+        assert_eq!("create-dir".parse::<Action>(), Ok(Action::CreateDir))
+    }
+
+    #[test]
+    fn test_parse_shell() {
+        assert_eq!("!chmod +x $f".parse::<Action>(), Ok(Action::Shell("chmod +x $f".to_string())))
+    }
+
+    #[test]
+    fn test_parse_unknown() {
+        assert!("not-an-action".parse::<Action>().is_err())
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for action in Action::builtins() {
+            assert_eq!(action.name().parse::<Action>(), Ok(action))
+        }
+    }
+}
@@ -1,11 +1,27 @@
-use std::path::Path;
+use std::collections::{BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
 
 use super::workingdir::WorkingDir;
 use super::file::File;
+use super::action::Action;
+use super::config::Config;
+use super::clipboard::{self, Clipboard};
+use super::glob;
+use super::keys::KeyParser;
+use super::filetype::FileType;
+use super::tab::{self, Pane, Tab};
+use super::sort::Sort;
+use super::filter::Filter;
+use super::users::{Identity, Users};
 
+use anyhow::{bail, Context};
 use tui::widgets::ListState;
 
+// How many directories each tab remembers
+const HISTORY_LEN: usize = 100;
+
 // Handles the state of the App
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InputMode {
     Normal,
     Editing,
@@ -13,22 +29,74 @@ pub enum InputMode {
     Visual,
 }
 
+/// What fm picks when it's used as a file chooser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choose {
+    Files,
+    Dir,
+}
+
 pub struct App {
     // Input mode
     pub input_mode: InputMode,
-    // Contains the user input in Editing mode
-    pub key_press: String,
+    // Keys pressed in Normal and Visual mode that aren't a binding yet
+    pub keys: KeyParser,
     // Info and helper methods for the cwd
     pub wd: WorkingDir,
     // Currently displayed files
     pub displayed_files: Vec<File>,
     // Current List state
     pub flist_state: ListState,
+    // First file shown in the list
+    pub scroll: usize,
+    // How many files fit in the list, set when it's drawn
+    pub list_height: usize,
     // msg contains error messages and keybinds
     pub err_msg: String,
     // Requesting for user input
     pub is_searching: bool,
     pub searching_for: String,
+    // User configuration
+    pub config: Config,
+    // Actions waiting to be run by the render loop along with their count
+    pub actions: VecDeque<(Action, Option<usize>)>,
+    // Whether the help is being displayed
+    pub show_help: bool,
+    // Preview of a file supplied by a plugin, None for files the
+    // previewers had nothing for or failed on so they aren't run again
+    pub preview: Option<(PathBuf, Option<String>)>,
+    // How far the preview of a file has been scrolled
+    pub preview_scroll: Option<(PathBuf, usize)>,
+    // Set when fm is used as a file chooser
+    pub choose: Option<Choose>,
+    // Paths picked in chooser mode
+    pub chosen: Vec<PathBuf>,
+    // Selected files, kept across searches and directory changes
+    pub selection: BTreeSet<PathBuf>,
+    // Where the range started in Visual mode
+    pub visual_anchor: Option<usize>,
+    // Files yanked or cut, shared by every tab
+    pub clipboard: Clipboard,
+    // Directories visited before the cwd in this tab
+    pub history: Vec<PathBuf>,
+    // The tabs that aren't shown, in order without the shown one
+    pub tabs: Vec<Tab>,
+    // Index of the shown tab among all of them
+    pub tab: usize,
+    // The inactive pane when there are two side by side
+    pub pane: Option<Pane>,
+    // Whether expanded directories are listed as a tree
+    pub tree: bool,
+    // Directories expanded in tree mode
+    pub expanded: BTreeSet<PathBuf>,
+    // Whether files are listed with the long listing columns
+    pub long: bool,
+    // Whether the details of the file under the cursor are displayed
+    pub show_info: bool,
+    // Names of the users and groups owning files
+    pub users: Users,
+    // Who fm runs as, for working out what can be done to files
+    pub identity: Identity,
 }
 
 impl App {
@@ -37,19 +105,359 @@ impl App {
     pub fn err(&mut self, msg: &str) {
         self.err_msg.clear();
         self.err_msg.push_str(msg);
-        self.to_error_mode()
+        self.error_mode()
+    }
+
+    /// Reports the files out of total an operation failed on, with why
+    pub fn err_files(&mut self, errors: &[String], total: usize) {
+        match errors.len() {
+            0 => {},
+            1 => self.err(&errors[0]),
+            n => self.err(&format!("{} of {} files weren't changed, {}", n, total, errors.join(", "))),
+        }
+    }
+
+    /// Queues an action to be run by the render loop
+    pub fn queue(&mut self, action: Action) {
+        self.actions.push_back((action, None))
+    }
+
+    /// Queues an action given a count like `5j`
+    pub fn queue_count(&mut self, action: Action, count: Option<usize>) {
+        self.actions.push_back((action, count))
     }
 
     /// Used when you want to end the input 
     /// and restore the context to default conditions
     pub fn end_input(&mut self) {
-        self.displayed_files = self.wd.files().to_vec();
+        self.reset_displayed_files();
         self.to_normal_mode();
         self.new_ctx();
     }
 
     pub fn clear_selection(&mut self) {
-        self.displayed_files.iter_mut().for_each(|f| f.is_selected = false);
+        self.selection.clear();
+    }
+
+    /// Selects the file at path or unselects it if it already is
+    pub fn toggle_selection(&mut self, path: &Path) {
+        if !self.selection.remove(path) {
+            self.selection.insert(path.to_owned());
+        }
+    }
+
+    /// Selects every file in the listing that isn't selected
+    /// and unselects the ones that are
+    pub fn invert_selection(&mut self) {
+        for file in &self.displayed_files {
+            if !self.selection.remove(&file.path) {
+                self.selection.insert(file.path.clone());
+            }
+        }
+    }
+
+    /// Selects every file in the listing
+    pub fn select_all(&mut self) {
+        self.selection.extend(self.displayed_files.iter().map(|f| f.path.clone()))
+    }
+
+    /// The files in the listing whose names match the glob pattern
+    pub fn glob_matches(&self, pattern: &str) -> Vec<PathBuf> {
+        if pattern.is_empty() {
+            return vec![]
+        }
+
+        self.displayed_files
+            .iter()
+            .filter(|f| glob::matches(pattern, &f.name))
+            .map(|f| f.path.clone())
+            .collect()
+    }
+
+    /// Anchors a range at the cursor, moving the cursor extends it
+    pub fn start_visual(&mut self) {
+        if let Some(i) = self.flist_state.selected() {
+            self.visual_anchor = Some(i);
+            self.input_mode = InputMode::Visual
+        }
+    }
+
+    /// Leaves Visual mode, the range is added to the selection if keep is set
+    pub fn end_visual(&mut self, keep: bool) {
+        if keep {
+            self.selection.extend(self.visual_range());
+        }
+        self.visual_anchor = None;
+        self.to_normal_mode()
+    }
+
+    /// The files between the anchor and the cursor in Visual mode
+    pub fn visual_range(&self) -> Vec<PathBuf> {
+        let (anchor, cursor) = match (self.visual_anchor, self.flist_state.selected()) {
+            (Some(anchor), Some(cursor)) => (anchor, cursor),
+            _ => return vec![],
+        };
+
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+        self.displayed_files
+            .iter()
+            .skip(start)
+            .take(end - start + 1)
+            .map(|f| f.path.clone())
+            .collect()
+    }
+
+    /// The selected files along with the range in Visual mode
+    pub fn marked(&self) -> BTreeSet<PathBuf> {
+        let mut marked = self.selection.clone();
+        marked.extend(self.visual_range());
+        marked
+    }
+
+    /// The files an operation applies to, the marked files
+    /// if there are any otherwise the file under the cursor
+    pub fn targets(&self) -> Vec<PathBuf> {
+        let marked = self.marked();
+        if marked.is_empty() {
+            self.selected_file().map(|f| f.path.clone()).into_iter().collect()
+        } else {
+            marked.into_iter().collect()
+        }
+    }
+
+    /// The files an operator applies to, count files from
+    /// the cursor if a count was given otherwise the targets
+    pub fn operands(&self, count: Option<usize>) -> Vec<PathBuf> {
+        match (count, self.flist_state.selected()) {
+            (Some(count), Some(cursor)) => self.displayed_files
+                .iter()
+                .skip(cursor)
+                .take(count)
+                .map(|f| f.path.clone())
+                .collect(),
+            _ => self.targets(),
+        }
+    }
+
+    /// Puts the operands in the clipboard and clears the selection
+    pub fn yank(&mut self, cut: bool, count: Option<usize>) {
+        let files = self.operands(count);
+        self.clipboard.yank(files, cut);
+        self.end_visual(false);
+        self.clear_selection();
+    }
+
+    /// Pastes the clipboard in the current directory and
+    /// moves the cursor to the first pasted file
+    pub fn paste(&mut self) {
+        let cwd = self.wd.cwd().clone();
+        let result = self.clipboard.paste(&cwd);
+        self.reload();
+
+        match result {
+            Ok(pasted) => if let Some(first) = pasted.first() {
+                self.select_file(first)
+            },
+            Err(e) => self.err(&format!("{:#}", e)),
+        }
+    }
+
+    /// Copies or moves the files an operation applies to into the other
+    /// pane's directory and shows the first one there
+    pub fn to_other_pane(&mut self, cut: bool, count: Option<usize>) {
+        let dir = match self.other_dir() {
+            Some(dir) => dir.to_owned(),
+            None => return self.err("There's no other pane, ctrl-w opens one"),
+        };
+        // Everything would just get a .~1~ copy or be renamed to one
+        if &dir == self.wd.cwd() {
+            return self.err("Both panes show the same directory")
+        }
+
+        let files = self.operands(count);
+        self.end_visual(false);
+        self.clear_selection();
+        let (moved, result) = clipboard::transfer(&files, &dir, cut);
+        self.reload();
+
+        if let Some(first) = moved.first() {
+            self.show_file(first)
+        }
+        if let Err(e) = result {
+            self.err(&format!("{:#}", e))
+        }
+    }
+
+    /// Swaps the state of the shown tab with tab
+    fn swap_tab(&mut self, tab: &mut Tab) {
+        std::mem::swap(&mut self.wd, &mut tab.wd);
+        std::mem::swap(&mut self.displayed_files, &mut tab.displayed_files);
+        std::mem::swap(&mut self.flist_state, &mut tab.flist_state);
+        std::mem::swap(&mut self.scroll, &mut tab.scroll);
+        std::mem::swap(&mut self.searching_for, &mut tab.searching_for);
+        std::mem::swap(&mut self.history, &mut tab.history);
+        self.is_searching = !self.searching_for.is_empty();
+        self.visual_anchor = None;
+    }
+
+    /// Opens a tab in the cwd after the shown one and switches to it
+    pub fn new_tab(&mut self) {
+        match Tab::new(&self.wd) {
+            Ok(mut tab) => {
+                self.swap_tab(&mut tab);
+                self.tabs.insert(self.tab, tab);
+                self.tab += 1;
+                self.reset_displayed_files();
+            },
+            Err(e) => self.err(&format!("{:#}", e)),
+        }
+    }
+
+    /// Closes the shown tab and switches to the one after it,
+    /// or the one before it if it was the last
+    pub fn close_tab(&mut self) {
+        if self.tabs.is_empty() {
+            return self.err("Can't close the last tab")
+        }
+
+        let next = self.tab.min(self.tabs.len() - 1);
+        let mut tab = self.tabs.remove(next);
+        self.swap_tab(&mut tab);
+        self.tab = next;
+        self.reload();
+    }
+
+    /// Switches to the tab at index, counting from 0
+    pub fn switch_tab(&mut self, index: usize) {
+        if index == self.tab || index > self.tabs.len() {
+            return
+        }
+
+        // Indices in self.tabs skip the shown tab
+        let mut tab = self.tabs.remove(if index < self.tab { index } else { index - 1 });
+        self.swap_tab(&mut tab);
+        self.tabs.insert(if index < self.tab { self.tab - 1 } else { self.tab }, tab);
+        self.tab = index;
+
+        // Files may have changed while it was hidden
+        self.reload();
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len() + 1
+    }
+
+    /// The names of every tab in order
+    pub fn tab_names(&self) -> Vec<String> {
+        let mut names = self.tabs.iter().map(Tab::name).collect::<Vec<_>>();
+        names.insert(self.tab, tab::tab_name(self.wd.cwd()));
+        names
+    }
+
+    /// Splits the screen into two panes in the cwd, or goes back to one
+    pub fn toggle_dual_pane(&mut self) {
+        if self.pane.take().is_some() {
+            return
+        }
+
+        match Tab::new(&self.wd) {
+            Ok(mut tab) => {
+                tab.reload();
+                self.pane = Some(Pane { tab, selection: BTreeSet::new(), left: false })
+            },
+            Err(e) => self.err(&format!("{:#}", e)),
+        }
+    }
+
+    /// Makes the other pane the active one
+    pub fn switch_pane(&mut self) {
+        if let Some(mut pane) = self.pane.take() {
+            self.swap_tab(&mut pane.tab);
+            std::mem::swap(&mut self.selection, &mut pane.selection);
+            pane.left = !pane.left;
+            self.pane = Some(pane);
+            self.reload();
+        }
+    }
+
+    /// The directory of the other pane in dual pane mode
+    pub fn other_dir(&self) -> Option<&Path> {
+        self.pane.as_ref().map(|pane| pane.tab.wd.cwd().as_path())
+    }
+
+    /// Moves the cursor to a file that was just made, files put in the
+    /// other pane's directory are shown there instead of following them
+    pub fn show_file(&mut self, path: &Path) {
+        if path.parent().is_none() || path.parent() != self.other_dir() {
+            return self.select_file(path)
+        }
+
+        self.reload();
+        if let Some(pane) = &mut self.pane {
+            pane.tab.select(path)
+        }
+    }
+
+    /// Changes which files are listed in every tab and pane
+    pub fn set_filter(&mut self, filter: Filter) {
+        for tab in &mut self.tabs {
+            tab.wd.set_filter(filter.clone());
+        }
+        if let Some(pane) = &mut self.pane {
+            pane.tab.wd.set_filter(filter.clone());
+        }
+
+        self.wd.set_filter(filter);
+        self.reload();
+    }
+
+    /// Shows or hides files starting with a dot
+    pub fn toggle_hidden(&mut self) {
+        let filter = self.wd.filter().clone();
+        self.set_filter(Filter { show_hidden: !filter.show_hidden, ..filter })
+    }
+
+    /// Shows or hides the files git ignores
+    pub fn toggle_gitignore(&mut self) {
+        let filter = self.wd.filter().clone();
+        self.set_filter(Filter { gitignore: !filter.gitignore, ..filter })
+    }
+
+    /// Changes how files are sorted in every tab and pane
+    /// keeping the cursor on the same file
+    pub fn set_sort(&mut self, sort: Sort) {
+        for tab in &mut self.tabs {
+            tab.wd.set_sort(sort);
+        }
+        if let Some(pane) = &mut self.pane {
+            pane.tab.wd.set_sort(sort);
+        }
+
+        self.wd.set_sort(sort);
+        self.reload();
+    }
+
+    /// Remembers a directory that was left
+    fn push_history(&mut self, dir: PathBuf) {
+        if self.history.last() != Some(&dir) {
+            self.history.push(dir);
+        }
+        if self.history.len() > HISTORY_LEN {
+            self.history.remove(0);
+        }
+    }
+
+    /// Goes back to the last directory visited in this tab,
+    /// skipping the ones that don't exist anymore
+    pub fn history_back(&mut self) {
+        while let Some(dir) = self.history.pop() {
+            if std::fs::read_dir(&dir).is_ok() && self.wd.set_cwd(&dir).is_ok() {
+                self.reset_displayed_files();
+                return self.new_ctx()
+            }
+        }
+
+        self.err("No directory to go back to")
     }
 
     /// Helper function to set the input mode to InputMode::Normal
@@ -62,11 +470,225 @@ impl App {
         self.input_mode = InputMode::Editing
     }
 
-    /// Helper function to set the input mode to InputMode::Editing
-    fn to_error_mode(&mut self) {
+    /// Helper function to set the input mode to InputMode::Error
+    fn error_mode(&mut self) {
         self.input_mode = InputMode::Error
     }
 
+    /// Moves the selection down the list and wraps up to the top
+    pub fn move_down(&mut self) {
+        if let Some(selected) = self.flist_state.selected() {
+            if selected + 1 >= self.displayed_files.len() {
+                self.flist_state.select(Some(0))
+            } else {
+                self.flist_state.select(Some(selected + 1))
+            }
+        }
+    }
+
+    /// Moves the selection up the list
+    pub fn move_up(&mut self) {
+        if let Some(selected) = self.flist_state.selected() {
+            self.flist_state.select(Some(selected.saturating_sub(1)))
+        }
+    }
+
+    /// Moves the selection down by count without wrapping
+    pub fn move_down_by(&mut self, count: usize) {
+        if let Some(selected) = self.flist_state.selected() {
+            let last = self.displayed_files.len().saturating_sub(1);
+            self.flist_state.select(Some(selected.saturating_add(count).min(last)))
+        }
+    }
+
+    /// Moves the selection up by count without wrapping
+    pub fn move_up_by(&mut self, count: usize) {
+        if let Some(selected) = self.flist_state.selected() {
+            self.flist_state.select(Some(selected.saturating_sub(count)))
+        }
+    }
+
+    /// Jumps to the nth file in the list, counting from 1
+    pub fn move_to(&mut self, n: usize) {
+        if !self.displayed_files.is_empty() {
+            let i = n.saturating_sub(1).min(self.displayed_files.len() - 1);
+            self.flist_state.select(Some(i))
+        }
+    }
+
+    /// How many files to keep between the cursor and the
+    /// edges of the list, at most half of it
+    fn scrolloff(&self) -> usize {
+        self.config.scrolloff.min(self.list_height.saturating_sub(1) / 2)
+    }
+
+    /// Scrolls the list so the cursor stays scrolloff files away from its edges
+    pub fn update_scroll(&mut self) {
+        let height = self.list_height.max(1);
+        let cursor = self.flist_state.selected().unwrap_or(0);
+        let scrolloff = self.scrolloff();
+
+        if cursor < self.scroll + scrolloff {
+            self.scroll = cursor.saturating_sub(scrolloff);
+        } else if cursor + scrolloff >= self.scroll + height {
+            self.scroll = cursor + scrolloff + 1 - height;
+        }
+
+        // Don't leave the end of the list empty
+        self.scroll = self.scroll.min(self.displayed_files.len().saturating_sub(height));
+    }
+
+    /// Scrolls the list and moves the cursor by the same number of files
+    pub fn scroll_by(&mut self, files: isize) {
+        if self.displayed_files.is_empty() {
+            return
+        }
+
+        if let Some(selected) = self.flist_state.selected() {
+            let len = self.displayed_files.len();
+            let max_scroll = len.saturating_sub(self.list_height);
+            self.scroll = self.scroll.saturating_add_signed(files).min(max_scroll);
+
+            // Keep the cursor scrolloff files away from the edges, unless
+            // it's an edge of the whole list, so the list doesn't scroll back
+            let scrolloff = self.scrolloff();
+            let low = if self.scroll == 0 { 0 } else { self.scroll + scrolloff };
+            let high = match self.scroll == max_scroll {
+                true => len - 1,
+                false => (self.scroll + self.list_height).saturating_sub(scrolloff + 1),
+            };
+
+            let cursor = selected.saturating_add_signed(files).min(len - 1);
+            self.flist_state.select(Some(cursor.clamp(low, high.max(low))))
+        }
+    }
+
+    /// How far the preview of the file at path has been scrolled
+    pub fn preview_scroll(&self, path: &Path) -> usize {
+        match &self.preview_scroll {
+            Some((scrolled, lines)) if scrolled == path => *lines,
+            _ => 0,
+        }
+    }
+
+    /// Scrolls the preview of the file under the cursor, which has
+    /// len lines, no further than its last line
+    pub fn scroll_preview(&mut self, lines: isize, len: usize) {
+        if let Some(path) = self.selected_file().map(|f| f.path.clone()) {
+            let scroll = self.preview_scroll(&path).saturating_add_signed(lines).min(len.saturating_sub(1));
+            self.preview_scroll = Some((path, scroll));
+        }
+    }
+
+    /// How many files are shown in the list
+    fn visible(&self) -> usize {
+        self.list_height.min(self.displayed_files.len().saturating_sub(self.scroll)).max(1)
+    }
+
+    /// Jumps to the nth file from the top of the screen
+    pub fn move_screen_top(&mut self, n: usize) {
+        let mut offset = n.saturating_sub(1);
+        if self.scroll > 0 {
+            offset = offset.max(self.scrolloff())
+        }
+
+        let i = self.scroll + offset.min(self.visible() - 1);
+        self.move_to(i + 1)
+    }
+
+    /// Jumps to the file in the middle of the screen
+    pub fn move_screen_middle(&mut self) {
+        let i = self.scroll + (self.visible() - 1) / 2;
+        self.move_to(i + 1)
+    }
+
+    /// Jumps to the nth file from the bottom of the screen
+    pub fn move_screen_bottom(&mut self, n: usize) {
+        let mut offset = n.saturating_sub(1);
+        if self.scroll + self.visible() < self.displayed_files.len() {
+            offset = offset.max(self.scrolloff())
+        }
+
+        let i = self.scroll + self.visible() - 1 - offset.min(self.visible() - 1);
+        self.move_to(i + 1)
+    }
+
+    /// Jumps to the first file in the list
+    pub fn move_top(&mut self) {
+        if !self.displayed_files.is_empty() {
+            self.flist_state.select(Some(0))
+        }
+    }
+
+    /// Jumps to the last file in the list
+    pub fn move_bottom(&mut self) {
+        if !self.displayed_files.is_empty() {
+            self.flist_state.select(Some(self.displayed_files.len() - 1))
+        }
+    }
+
+    /// Changes the working directory to dir
+    pub fn cd(&mut self, dir: &Path) -> anyhow::Result<()> {
+        std::fs::read_dir(dir).with_context(|| format!("Can't open {}", dir.display()))?;
+        self.push_history(self.wd.cwd().clone());
+        self.wd.set_cwd(dir)?;
+        self.reset_displayed_files();
+        self.new_ctx();
+        Ok(())
+    }
+
+    /// Queues the actions of a user defined command or a builtin action
+    /// by name, user defined commands take priority over the builtins
+    pub fn run_command(&mut self, name: &str) -> Result<(), String> {
+        let actions = match self.config.command(name) {
+            Some(cmd) => cmd.actions(),
+            None => vec![name.parse::<Action>()?],
+        };
+
+        actions.into_iter().for_each(|a| self.queue(a));
+        Ok(())
+    }
+
+    /// Rereads the current directory keeping the search and
+    /// the cursor on the same file if it still exists
+    pub fn reload(&mut self) {
+        // Files removed from under us can't be selected anymore
+        self.selection.retain(|p| p.symlink_metadata().is_ok());
+
+        if let Some(pane) = &mut self.pane {
+            pane.selection.retain(|p| p.symlink_metadata().is_ok());
+            pane.tab.reload();
+        }
+
+        // Someone removed the directory from under us
+        if !self.wd.cwd().is_dir() {
+            let parent = self.wd.cwd().ancestors().find(|p| p.is_dir()).map(Path::to_owned);
+            if let Some(parent) = parent {
+                self.err("Directory was removed");
+                let _ = self.wd.set_cwd(&parent);
+                self.reset_displayed_files();
+                return self.new_ctx()
+            }
+        }
+
+        let current = self.selected_file().map(|f| f.path().to_owned());
+        let index = self.flist_state.selected().unwrap_or(0);
+
+        if let Err(e) = self.wd.update() {
+            return self.err(&e.to_string())
+        }
+
+        self.update_displayed_files(None);
+
+        let position = self.displayed_files.iter().position(|f| Some(&f.path) == current.as_ref());
+        match position {
+            Some(i) => self.flist_state.select(Some(i)),
+            None if self.displayed_files.is_empty() => self.flist_state.select(None),
+            // The file is gone so stay where we were
+            None => self.flist_state.select(Some(index.min(self.displayed_files.len() - 1))),
+        }
+    }
+
     /// Update the displayed files for a search or if a 
     /// file was deleted or added, takes an optional needle 
     /// argument for searching otherwise it just displays 
@@ -74,17 +696,15 @@ impl App {
     pub fn update_displayed_files(&mut self, needle: Option<&str>) {
         if let Some(needle) = needle {
             self.searching_for = needle.to_string();
-            self.displayed_files = self.wd.files()
-                .iter()
+            self.displayed_files = self.listing()
+                .into_iter()
                 .filter(|f| f.name.starts_with(&self.searching_for))
-                .cloned()
                 .collect();
         // } else {
         } else if !self.searching_for.is_empty() {
-             self.displayed_files = self.wd.files()
-                .iter()
+             self.displayed_files = self.listing()
+                .into_iter()
                 .filter(|f| f.name.starts_with(&self.searching_for))
-                .cloned()
                 .collect();
 
         } else {
@@ -94,10 +714,19 @@ impl App {
     }
 
     pub fn select_file(&mut self, needle: &Path) {
+        // Files in expanded directories are shown without leaving the cwd
+        if self.tree && needle.starts_with(self.wd.cwd()) {
+            self.update_displayed_files(None);
+            if let Some(i) = self.displayed_files.iter().position(|f| f.path == needle) {
+                return self.flist_state.select(Some(i))
+            }
+        }
+
         // get the parent directory
         if let Some(parent) = needle.parent() {
             // Check to see if we should switch directories or not
             if self.wd.cwd().ne(parent) {
+                self.push_history(self.wd.cwd().clone());
                 match self.wd.set_cwd(parent) {
                     Ok(_) => self.update_displayed_files(None),
                     Err(e) => self.err(&e.to_string()),
@@ -118,8 +747,122 @@ impl App {
         }
     }
 
+    /// Returns the paths to pick in chooser mode, the selected files
+    /// if there are any otherwise the file under the cursor. When choosing
+    /// a directory and the cursor is on a file the cwd is picked
+    pub fn choices(&self) -> Vec<PathBuf> {
+        let is_dir = |f: &&File| f.ftype == FileType::Directory;
+        let selected = self.selection.iter().cloned();
+
+        let mut choices = match self.choose {
+            Some(Choose::Files) => selected.filter(|p| !p.is_dir()).collect(),
+            Some(Choose::Dir) => selected.filter(|p| p.is_dir()).collect(),
+            None => Vec::new(),
+        };
+
+        if choices.is_empty() {
+            match (self.choose, self.selected_file()) {
+                (Some(Choose::Files), Some(f)) if !is_dir(&f) => choices.push(f.path.clone()),
+                (Some(Choose::Dir), Some(f)) if is_dir(&f) => choices.push(f.path.clone()),
+                (Some(Choose::Dir), _) => choices.push(self.wd.cwd().clone()),
+                _ => {}
+            }
+        }
+
+        choices
+    }
+
     pub fn reset_displayed_files(&mut self) {
-        self.displayed_files = self.wd.files().to_vec();
+        self.displayed_files = self.listing();
+    }
+
+    /// The files in the cwd, in tree mode followed by the
+    /// files of the ones that are expanded
+    pub fn listing(&self) -> Vec<File> {
+        let cwd = self.wd.files();
+        let mut files = Vec::with_capacity(cwd.len());
+        self.push_tree(cwd, 0, &mut files);
+        files
+    }
+
+    fn push_tree(&self, files: &[File], depth: usize, listing: &mut Vec<File>) {
+        for file in files {
+            let expand = self.tree && file.ftype == FileType::Directory && self.expanded.contains(&file.path);
+            listing.push(File { depth, ..file.clone() });
+
+            // Each level is only read once it's expanded, ones that
+            // can't be read are shown empty
+            if expand {
+                if let Ok(children) = self.wd.get_files(&file.path) {
+                    self.push_tree(&children, depth + 1, listing)
+                }
+            }
+        }
+    }
+
+    /// Moves the cursor to path, or the closest ancestor of it that's listed
+    fn select_listed(&mut self, path: &Path) {
+        let i = path.ancestors().find_map(|p| self.displayed_files.iter().position(|f| f.path == p));
+        match i {
+            Some(i) => self.flist_state.select(Some(i)),
+            None => self.new_list_state(),
+        }
+    }
+
+    /// Switches between listing only names and the long listing
+    pub fn toggle_long(&mut self) {
+        self.long = !self.long;
+    }
+
+    /// Switches between listing the cwd and a tree of it
+    pub fn toggle_tree(&mut self) {
+        let current = self.selected_file().map(|f| f.path.clone());
+        self.tree = !self.tree;
+        self.update_displayed_files(None);
+        if let Some(path) = current {
+            self.select_listed(&path)
+        }
+    }
+
+    /// Expands the directory under the cursor in place, turning on tree mode
+    pub fn expand(&mut self) {
+        let dir = match self.selected_file() {
+            Some(f) if f.ftype == FileType::Directory => f.path.clone(),
+            _ => return,
+        };
+
+        self.tree = true;
+        self.expanded.insert(dir.clone());
+        self.update_displayed_files(None);
+        self.select_listed(&dir);
+    }
+
+    /// Collapses the directory under the cursor, or the one the
+    /// file under the cursor is in and moves to it
+    pub fn collapse(&mut self) {
+        let path = match self.selected_file() {
+            Some(f) => f.path.clone(),
+            None => return,
+        };
+
+        if !self.expanded.remove(&path) {
+            match path.parent() {
+                Some(parent) if parent != self.wd.cwd() => self.expanded.remove(parent),
+                _ => return,
+            };
+        }
+
+        self.update_displayed_files(None);
+        self.select_listed(&path);
+    }
+
+    /// The expanded directories shown in the tree
+    pub fn expanded_dirs(&self) -> Vec<PathBuf> {
+        self.displayed_files
+            .iter()
+            .filter(|f| self.expanded.contains(&f.path))
+            .map(|f| f.path.clone())
+            .collect()
     }
     
     /// Basic opereations for opening a new context
@@ -132,15 +875,18 @@ impl App {
     // Shifts the context to the next directory 
     pub fn wd_forward(&mut self) {
         let selected_path = self.selected_file().unwrap().path().to_owned();
+        self.push_history(self.wd.cwd().clone());
         self.wd.forward(&selected_path); 
-        self.displayed_files = self.wd.files().to_vec();
+        self.reset_displayed_files();
         self.new_ctx();
     }
 
     /// Shifts the context back one directory
     pub fn wd_back(&mut self) {
+        let cwd = self.wd.cwd().clone();
         if self.wd.back() {
-            self.displayed_files = self.wd.files().to_vec();
+            self.push_history(cwd);
+            self.reset_displayed_files();
             self.new_ctx();
         }
     }
@@ -184,36 +930,139 @@ impl App {
         }
     }
 
-    /// Creates a default new App
+    /// Creates a default new App in the current directory, 
+    /// exits with the reason if that fails
     pub fn new() -> Self {
-        let input_mode = InputMode::Normal;
-        let key_press = String::with_capacity(2);
-        let wd = match WorkingDir::new(None) {
-            Ok(w) => w,
-            Err(_) => {
-                eprintln!("Error starting fm");
+        match Self::open(None, None) {
+            Ok(app) => app,
+            Err(e) => {
+                eprintln!("fm: {:#}", e);
                 std::process::exit(1)
-            } 
+            }
+        }
+    }
+
+    /// Creates a new App in the directory at path, or the current directory.
+    /// If path is a file its directory is opened with the file selected.
+    /// The config is read from config_path instead of the default location
+    pub fn open(path: Option<&Path>, config_path: Option<&Path>) -> anyhow::Result<Self> {
+        let config_path = match config_path {
+            Some(path) if !path.exists() => bail!("Config file {} doesn't exist", path.display()),
+            Some(path) => Some(path.to_owned()),
+            None => Config::default_path(),
+        };
+
+        let (config, config_err) = match config_path {
+            Some(path) => match Config::load(&path) {
+                Ok(config) => (config, None),
+                Err(e) => (Config::default(), Some(format!("{:#}", e))),
+            },
+            None => (Config::default(), None),
+        };
+
+        let mut app = Self::with_config(path, config)?;
+        if let Some(e) = config_err {
+            app.err(&e)
+        }
+
+        Ok(app)
+    }
+
+    /// Creates a new App like `open` with config instead of reading one
+    pub fn with_config(path: Option<&Path>, config: Config) -> anyhow::Result<Self> {
+        let input_mode = InputMode::Normal;
+        let keys = KeyParser::default();
+
+        let (dir, file) = match path {
+            Some(path) => {
+                let mdata = std::fs::metadata(path)
+                    .with_context(|| format!("Can't open {}", path.display()))?;
+
+                if mdata.is_dir() {
+                    (Some(std::fs::canonicalize(path)?), None)
+                } else {
+                    // Only the directory is canonicalized so symlinks stay selected
+                    let parent = match path.parent() {
+                        Some(parent) if !parent.as_os_str().is_empty() => parent,
+                        _ => Path::new("."),
+                    };
+                    let parent = std::fs::canonicalize(parent)?;
+                    let file = path.file_name().map(|name| parent.join(name));
+                    (Some(parent), file)
+                }
+            },
+            None => (None, None),
         };
-        
-        let displayed_files = wd.files().to_owned();
 
         let mut flist_state = ListState::default();
         flist_state.select(Some(0));
+        let scroll = 0;
+        let list_height = 0;
         let err_msg = String::with_capacity(15);
         let is_searching = false;
         let searching_for = String::new();
+        let actions = VecDeque::new();
+        let show_help = false;
+        let preview = None;
+        let preview_scroll = None;
+        let choose = None;
+        let chosen = Vec::new();
+        let selection = BTreeSet::new();
+        let visual_anchor = None;
+        let clipboard = Clipboard::default();
+        let history = Vec::new();
+        let tabs = Vec::new();
+        let tab = 0;
+        let pane = None;
+        let tree = false;
+        let expanded = BTreeSet::new();
+        let long = config.long;
+        let show_info = false;
+        let users = Users::load();
+        let identity = Identity::current();
 
-        Self {
+        let wd = WorkingDir::new(dir.as_deref(), config.filter(), config.sort)?;
+        let displayed_files = wd.files().to_owned();
+
+        let mut app = Self {
             input_mode,
-            key_press,
+            keys,
             wd,
             displayed_files,
             flist_state,
+            scroll,
+            list_height,
             err_msg,
             is_searching,
             searching_for,
+            config,
+            actions,
+            show_help,
+            preview,
+            preview_scroll,
+            choose,
+            chosen,
+            selection,
+            visual_anchor,
+            clipboard,
+            history,
+            tabs,
+            tab,
+            pane,
+            tree,
+            expanded,
+            long,
+            show_info,
+            users,
+            identity,
+        };
+        app.reset_displayed_files();
+
+        if let Some(file) = file {
+            app.select_file(&file)
         }
+
+        Ok(app)
     }
 }
 
@@ -222,3 +1071,457 @@ impl Default for App {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{App, Choose, InputMode};
+    use crate::config::Config;
+    use crate::filetype::FileType;
+    use std::path::{Path, PathBuf};
+
+    fn root(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fm-app-{}-{}", name, std::process::id()))
+    }
+
+    /// A directory laid out like a small crate for the tests to move around in
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = root(name);
+        for sub in ["src/bin", "src/lib"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for file in ["Cargo.toml", "README.md", ".gitignore", "src/bin/fm.rs", "src/lib/app.rs", "src/lib/glob.rs", "src/lib/lib.rs"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        dir.canonicalize().unwrap()
+    }
+
+    /// An App in a testing_dir with the default config rather than the user's
+    fn testing_app(name: &str) -> App {
+        App::with_config(Some(&testing_dir(name)), Config::default()).unwrap()
+    }
+
+    fn choosing_app(name: &str, choose: Choose) -> App {
+        let mut app = testing_app(name);
+        app.choose = Some(choose);
+        app
+    }
+
+    fn remove(name: &str) {
+        std::fs::remove_dir_all(root(name)).unwrap();
+    }
+
+    #[test]
+    fn test_open_file() {
+        let dir = testing_dir("open");
+        let app = App::with_config(Some(&dir.join("src/lib/app.rs")), Config::default()).unwrap();
+        assert_eq!(app.wd.cwd(), &dir.join("src/lib"));
+        assert_eq!(app.selected_file().unwrap().name, "app.rs");
+        remove("open");
+    }
+
+    #[test]
+    fn test_open_missing() {
+        assert!(App::with_config(Some(Path::new("does/not/exist")), Config::default()).is_err());
+        assert!(App::open(None, Some(Path::new("does/not/exist.yml"))).is_err());
+    }
+
+    #[test]
+    fn test_choose_selected_files() {
+        let mut app = choosing_app("choose-files", Choose::Files);
+        let files = app.displayed_files.iter()
+            .filter(|f| f.ftype != FileType::Directory)
+            .take(2)
+            .map(|f| f.path.clone())
+            .collect::<Vec<_>>();
+        files.iter().for_each(|p| app.toggle_selection(p));
+
+        let choices = app.choices();
+        assert_eq!(choices.len(), 2);
+        assert!(choices.iter().all(|p| p.is_file()));
+        remove("choose-files");
+    }
+
+    #[test]
+    fn test_choose_dir_from_file() {
+        let mut app = choosing_app("choose-dir", Choose::Dir);
+        let file = app.wd.cwd().join("Cargo.toml");
+        app.select_file(&file);
+        assert_eq!(app.choices(), vec![app.wd.cwd().clone()]);
+        remove("choose-dir");
+    }
+
+    #[test]
+    fn test_choose_file_from_dir() {
+        let mut app = choosing_app("choose-from-dir", Choose::Files);
+        let dir = app.wd.cwd().join("src");
+        app.select_file(&dir);
+        assert!(app.choices().is_empty());
+        remove("choose-from-dir");
+    }
+
+    #[test]
+    fn test_reload_keeps_cursor() {
+        let dir = std::env::temp_dir().join(format!("fm-reload-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["b", "c"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+
+        let mut app = App::with_config(Some(&dir), Config::default()).unwrap();
+        app.select_file(&dir.join("c"));
+
+        std::fs::write(dir.join("a"), "").unwrap();
+        app.reload();
+
+        assert_eq!(app.selected_file().unwrap().name, "c");
+        assert_eq!(app.displayed_files.len(), 3);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_selection_survives_cd() {
+        let mut app = testing_app("selection-survives-cd");
+        let file = app.wd.cwd().join("Cargo.toml");
+        app.toggle_selection(&file);
+
+        app.cd(&app.wd.cwd().join("src")).unwrap();
+        app.update_displayed_files(Some("li"));
+        assert!(app.selection.contains(&file));
+        assert_eq!(app.targets(), vec![file.clone()]);
+
+        app.toggle_selection(&file);
+        assert!(app.selection.is_empty());
+        remove("selection-survives-cd");
+    }
+
+    #[test]
+    fn test_visual_range() {
+        let mut app = testing_app("visual-range");
+        app.flist_state.select(Some(1));
+        app.start_visual();
+        app.move_down();
+        app.move_down();
+
+        let range = app.visual_range();
+        assert_eq!(range.len(), 3);
+        assert_eq!(range[0], app.displayed_files[1].path);
+        let mut targets = app.targets();
+        targets.sort();
+        let mut sorted = range.clone();
+        sorted.sort();
+        assert_eq!(targets, sorted);
+
+        app.end_visual(true);
+        assert_eq!(app.selection.len(), 3);
+        assert!(app.visual_range().is_empty());
+        remove("visual-range");
+    }
+
+    #[test]
+    fn test_invert_selection() {
+        let mut app = testing_app("invert-selection");
+        let first = app.displayed_files[0].path.clone();
+        app.toggle_selection(&first);
+
+        app.invert_selection();
+        assert!(!app.selection.contains(&first));
+        assert_eq!(app.selection.len(), app.displayed_files.len() - 1);
+
+        app.select_all();
+        assert_eq!(app.selection.len(), app.displayed_files.len());
+        remove("invert-selection");
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        let app = testing_app("glob-matches");
+        assert_eq!(app.glob_matches("*.toml"), vec![app.wd.cwd().join("Cargo.toml")]);
+        assert!(app.glob_matches("").is_empty());
+        remove("glob-matches");
+    }
+
+    #[test]
+    fn test_counts() {
+        let mut app = testing_app("counts");
+        let len = app.displayed_files.len();
+
+        app.move_down_by(100);
+        assert_eq!(app.flist_state.selected(), Some(len - 1));
+        app.move_up_by(2);
+        assert_eq!(app.flist_state.selected(), Some(len - 3));
+        app.move_to(2);
+        assert_eq!(app.flist_state.selected(), Some(1));
+
+        let operands = app.operands(Some(2));
+        assert_eq!(operands, vec![app.displayed_files[1].path.clone(), app.displayed_files[2].path.clone()]);
+        remove("counts");
+    }
+
+    // An App showing 10 of 100 files
+    fn scrolling_app(name: &str, scrolloff: usize) -> App {
+        let mut app = testing_app(name);
+        remove(name);
+        let file = app.displayed_files[0].clone();
+        app.displayed_files = vec![file; 100];
+        app.list_height = 10;
+        app.config.scrolloff = scrolloff;
+        app
+    }
+
+    #[test]
+    fn test_scrolloff() {
+        let mut app = scrolling_app("scrolloff", 3);
+        app.move_to(7);
+        app.update_scroll();
+        assert_eq!(app.scroll, 0);
+
+        app.move_to(8);
+        app.update_scroll();
+        assert_eq!(app.scroll, 1);
+
+        app.move_bottom();
+        app.update_scroll();
+        assert_eq!(app.scroll, 90);
+
+        app.move_to(93);
+        app.update_scroll();
+        assert_eq!(app.scroll, 89);
+    }
+
+    #[test]
+    fn test_scroll_by() {
+        let mut app = scrolling_app("scroll-by", 0);
+        app.move_to(3);
+        app.scroll_by(5);
+        assert_eq!((app.scroll, app.flist_state.selected()), (5, Some(7)));
+
+        app.scroll_by(-10);
+        assert_eq!((app.scroll, app.flist_state.selected()), (0, Some(0)));
+
+        // Nothing to scroll in an empty directory
+        app.displayed_files.clear();
+        app.scroll_by(5);
+        assert_eq!((app.scroll, app.flist_state.selected()), (0, Some(0)));
+    }
+
+    #[test]
+    fn test_scroll_preview() {
+        let mut app = testing_app("scroll-preview");
+        let file = app.selected_file().unwrap().path.clone();
+        app.scroll_preview(3, 10);
+        assert_eq!(app.preview_scroll(&file), 3);
+
+        // It stops at the last line and doesn't go above the first
+        app.scroll_preview(isize::MAX, 10);
+        assert_eq!(app.preview_scroll(&file), 9);
+        app.scroll_preview(-20, 10);
+        assert_eq!(app.preview_scroll(&file), 0);
+        app.scroll_preview(3, 0);
+        assert_eq!(app.preview_scroll(&file), 0);
+        remove("scroll-preview");
+    }
+
+    #[test]
+    fn test_scroll_keeps_scrolloff() {
+        let mut app = scrolling_app("scroll-keeps-scrolloff", 3);
+        app.scroll_by(5);
+        assert_eq!((app.scroll, app.flist_state.selected()), (5, Some(8)));
+
+        app.move_bottom();
+        app.scroll = 90;
+        app.scroll_by(-5);
+        assert_eq!((app.scroll, app.flist_state.selected()), (85, Some(91)));
+    }
+
+    #[test]
+    fn test_screen_jumps() {
+        let mut app = scrolling_app("screen-jumps", 2);
+        app.scroll = 20;
+
+        app.move_screen_top(1);
+        assert_eq!(app.flist_state.selected(), Some(22));
+        app.move_screen_middle();
+        assert_eq!(app.flist_state.selected(), Some(24));
+        app.move_screen_bottom(1);
+        assert_eq!(app.flist_state.selected(), Some(27));
+        app.move_screen_bottom(5);
+        assert_eq!(app.flist_state.selected(), Some(25));
+    }
+
+    #[test]
+    fn test_tabs() {
+        let mut app = testing_app("tabs");
+        let root = app.wd.cwd().clone();
+
+        app.new_tab();
+        app.cd(&root.join("src")).unwrap();
+        app.update_displayed_files(Some("li"));
+        app.new_tab();
+        assert_eq!((app.tab, app.tab_count()), (2, 3));
+        // New tabs start where the last one was, without its search
+        assert_eq!(app.wd.cwd(), &root.join("src"));
+        assert!(app.searching_for.is_empty());
+
+        app.switch_tab(0);
+        assert_eq!(app.wd.cwd(), &root);
+        app.switch_tab(1);
+        assert_eq!(app.searching_for, "li");
+        assert_eq!(app.tab_names(), vec![super::tab::tab_name(&root), "src".into(), "src".into()]);
+
+        app.close_tab();
+        assert_eq!((app.tab, app.tab_count()), (1, 2));
+        assert!(app.searching_for.is_empty());
+        app.close_tab();
+        app.close_tab();
+        assert_eq!(app.tab_count(), 1);
+        assert_eq!(app.wd.cwd(), &root);
+        remove("tabs");
+    }
+
+    #[test]
+    fn test_history_back() {
+        let mut app = testing_app("history-back");
+        let root = app.wd.cwd().clone();
+        app.cd(&root.join("src")).unwrap();
+        app.cd(&root.join("src/lib")).unwrap();
+        app.wd_back();
+
+        app.history_back();
+        assert_eq!(app.wd.cwd(), &root.join("src/lib"));
+        app.history_back();
+        assert_eq!(app.wd.cwd(), &root.join("src"));
+        app.history_back();
+        assert_eq!(app.wd.cwd(), &root);
+        remove("history-back");
+    }
+
+    #[test]
+    fn test_dual_pane() {
+        let mut app = testing_app("dual-pane");
+        let root = app.wd.cwd().clone();
+        app.toggle_dual_pane();
+        assert_eq!(app.other_dir(), Some(root.as_path()));
+
+        app.cd(&root.join("src")).unwrap();
+        app.toggle_selection(&root.join("src/bin"));
+        app.switch_pane();
+        assert_eq!(app.wd.cwd(), &root);
+        assert_eq!(app.other_dir(), Some(root.join("src").as_path()));
+        // Each pane has its own selection
+        assert!(app.selection.is_empty());
+
+        app.switch_pane();
+        assert!(app.selection.contains(&root.join("src/bin")));
+        assert!(!app.pane.as_ref().unwrap().left);
+
+        app.toggle_dual_pane();
+        assert!(app.other_dir().is_none());
+        remove("dual-pane");
+    }
+
+    #[test]
+    fn test_to_other_pane() {
+        let mut app = testing_app("other-pane");
+        let root = app.wd.cwd().clone();
+        app.toggle_dual_pane();
+        app.switch_pane();
+        app.cd(&root.join("src/lib")).unwrap();
+        app.switch_pane();
+        app.cd(&root.join("src")).unwrap();
+
+        // Directories are copied with everything in them
+        app.toggle_selection(&root.join("src/bin"));
+        app.to_other_pane(false, None);
+        assert!(root.join("src/lib/bin/fm.rs").exists());
+        assert!(root.join("src/bin/fm.rs").exists());
+        assert!(app.selection.is_empty());
+
+        app.toggle_selection(&root.join("src/bin"));
+        app.to_other_pane(true, None);
+        assert!(root.join("src/lib/bin.~1~/fm.rs").exists());
+        assert!(!root.join("src/bin").exists());
+        remove("other-pane");
+    }
+
+    #[test]
+    fn test_to_other_pane_same_dir() {
+        let mut app = testing_app("other-pane-same-dir");
+        let root = app.wd.cwd().clone();
+        app.toggle_dual_pane();
+
+        app.select_file(&root.join("Cargo.toml"));
+        app.to_other_pane(true, None);
+        assert_eq!(app.input_mode, InputMode::Error);
+        app.to_other_pane(false, None);
+        assert!(root.join("Cargo.toml").exists());
+        assert!(!root.join("Cargo.toml.~1~").exists());
+        remove("other-pane-same-dir");
+    }
+
+    #[test]
+    fn test_tree() {
+        let mut app = testing_app("tree");
+        let root = app.wd.cwd().clone();
+        app.select_file(&root.join("src"));
+        app.expand();
+        app.move_down();
+        assert_eq!(app.selected_file().unwrap().path, root.join("src/bin"));
+        assert_eq!(app.selected_file().unwrap().depth, 1);
+
+        app.move_down();
+        app.expand();
+        app.move_down();
+        assert_eq!(app.selected_file().unwrap().depth, 2);
+
+        // Searching matches inside the expanded directories
+        app.update_displayed_files(Some("glob"));
+        assert_eq!(app.displayed_files[0].path, root.join("src/lib/glob.rs"));
+        app.end_input();
+
+        // Collapsing from a file closes its directory and moves to it
+        app.select_file(&root.join("src/lib/app.rs"));
+        app.collapse();
+        assert_eq!(app.selected_file().unwrap().path, root.join("src/lib"));
+        app.toggle_tree();
+        assert_eq!(app.selected_file().unwrap().path, root.join("src"));
+        remove("tree");
+    }
+
+    #[test]
+    fn test_sort_keeps_cursor() {
+        use crate::sort::{Sort, SortBy};
+
+        let mut app = testing_app("sort-keeps-cursor");
+        let file = app.wd.cwd().join("Cargo.toml");
+        app.select_file(&file);
+        app.toggle_dual_pane();
+
+        app.set_sort(Sort { by: SortBy::Type, reverse: true, dirs_first: false });
+        assert_eq!(app.selected_file().unwrap().path, file);
+        // Reversed by type the files come before the directories
+        assert_ne!(app.displayed_files[0].ftype, FileType::Directory);
+        assert_eq!(app.displayed_files.last().unwrap().ftype, FileType::Directory);
+        // The other pane and the parent column are sorted the same way
+        let pane = &app.pane.as_ref().unwrap().tab.displayed_files;
+        assert_eq!(pane.last().unwrap().ftype, FileType::Directory);
+        let parent = app.wd.get_files(app.wd.cwd().parent().unwrap()).unwrap();
+        assert_eq!(parent.last().unwrap().ftype, FileType::Directory);
+        remove("sort-keeps-cursor");
+    }
+
+    #[test]
+    fn test_toggle_hidden() {
+        let mut app = testing_app("toggle-hidden");
+        let gitignore = app.wd.cwd().join(".gitignore");
+        app.select_file(&gitignore);
+
+        app.toggle_hidden();
+        assert!(app.displayed_files.iter().all(|f| !f.name.starts_with('.')));
+        assert!(app.listing().iter().all(|f| f.path != gitignore));
+
+        app.toggle_hidden();
+        assert!(app.displayed_files.iter().any(|f| f.path == gitignore));
+        remove("toggle-hidden");
+    }
+}
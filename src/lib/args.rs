@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use super::app::Choose;
+
+/// Printed for `--help`
+pub const USAGE: &str = "\
+Usage: fm [OPTIONS] [PATH]
+
+Opens PATH, or the current directory. If PATH is a file its
+directory is opened with the file selected.
+
+Options:
+    --config <file>          Read the config from file
+    --last-dir-path <file>   Write the last directory to file on exit
+    --print-last-dir         Print the last directory to stdout on exit
+    --choose-files <file>    Write the chosen files to file and exit, - is stdout
+    --choose-dir <file>      Write the chosen directory to file and exit, - is stdout
+    -remote [pid] <command>  Send a command to a running fm and print the reply
+    -h, --help               Print this help
+    -V, --version            Print the version";
+
+/// Options passed to fm on the command line
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+    /// Directory to open or file to select
+    pub path: Option<PathBuf>,
+    /// Config file to use instead of the default
+    pub config: Option<PathBuf>,
+    /// Print the usage and exit
+    pub help: bool,
+    /// Print the version and exit
+    pub version: bool,
+    /// Command to send to a running fm
+    pub remote: Option<Vec<String>>,
+    /// File to write the last directory to on exit
+    pub last_dir_path: Option<PathBuf>,
+    /// Print the last directory to stdout on exit
+    pub print_last_dir: bool,
+    /// Pick files and write them to this file, `-` is stdout
+    pub choose_files: Option<PathBuf>,
+    /// Pick a directory and write it to this file, `-` is stdout
+    pub choose_dir: Option<PathBuf>,
+}
+
+impl Args {
+    /// Parses the arguments, not including the program name
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Everything after `--` is the path
+            if arg == "--" {
+                if let Some(path) = args.next() {
+                    parsed.set_path(path)?;
+                }
+                continue
+            }
+
+            // Everything after `-remote` is the command to send
+            if arg == "-remote" || arg == "--remote" {
+                parsed.remote = Some(args.by_ref().collect());
+                break
+            }
+
+            if !arg.starts_with('-') || arg == "-" {
+                parsed.set_path(arg)?;
+                continue
+            }
+
+            // Options can be given as `--opt value` or `--opt=value`
+            let (opt, mut value) = match arg.split_once('=') {
+                Some((opt, value)) if opt.starts_with("--") => (opt.to_string(), Some(value.to_string())),
+                _ => (arg, None),
+            };
+
+            // Takes the value of an option that needs one
+            let mut take_value = |what: &str| {
+                value.take().or_else(|| args.next())
+                    .map(PathBuf::from)
+                    .ok_or(format!("{} needs a {}", opt, what))
+            };
+
+            match opt.as_str() {
+                "--config" => parsed.config = Some(take_value("file")?),
+                "--last-dir-path" => parsed.last_dir_path = Some(take_value("file")?),
+                "--print-last-dir" => parsed.print_last_dir = true,
+                "--choose-files" => parsed.choose_files = Some(take_value("file")?),
+                "--choose-dir" => parsed.choose_dir = Some(take_value("file")?),
+                "-h" | "--help" => parsed.help = true,
+                "-V" | "--version" => parsed.version = true,
+                _ => return Err(format!("Unknown option: {}", opt)),
+            }
+        }
+
+        if parsed.choose_files.is_some() && parsed.choose_dir.is_some() {
+            return Err("--choose-files and --choose-dir can't be used together".to_string())
+        }
+
+        Ok(parsed)
+    }
+
+    fn set_path(&mut self, path: String) -> Result<(), String> {
+        if self.path.is_some() {
+            return Err(format!("Only one path can be opened, got another: {}", path))
+        }
+
+        self.path = Some(PathBuf::from(path));
+        Ok(())
+    }
+
+    /// What to pick and where to write it when fm is used as a file chooser
+    pub fn chooser(&self) -> Option<(Choose, &Path)> {
+        match (&self.choose_files, &self.choose_dir) {
+            (Some(path), _) => Some((Choose::Files, path)),
+            (None, Some(path)) => Some((Choose::Dir, path)),
+            (None, None) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Args;
+    use crate::app::Choose;
+    use std::path::{Path, PathBuf};
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_no_args() {
+        assert_eq!(parse(&[]), Ok(Args::default()))
+    }
+
+    #[test]
+    fn test_last_dir_path() {
+        let expected = Some(PathBuf::from("/tmp/fm"));
+        assert_eq!(parse(&["--last-dir-path", "/tmp/fm"]).unwrap().last_dir_path, expected);
+        assert_eq!(parse(&["--last-dir-path=/tmp/fm"]).unwrap().last_dir_path, expected);
+        assert!(parse(&["--last-dir-path"]).is_err());
+    }
+
+    #[test]
+    fn test_path() {
+        let args = parse(&["--config", "fm.yml", "src/lib"]).unwrap();
+        assert_eq!(args.path, Some(PathBuf::from("src/lib")));
+        assert_eq!(args.config, Some(PathBuf::from("fm.yml")));
+
+        assert_eq!(parse(&["--", "-dashed"]).unwrap().path, Some(PathBuf::from("-dashed")));
+        assert!(parse(&["one", "two"]).is_err());
+    }
+
+    #[test]
+    fn test_remote() {
+        let args = parse(&["-remote", "send", "!echo", "-x"]).unwrap();
+        assert_eq!(args.remote, Some(vec!["send".to_string(), "!echo".to_string(), "-x".to_string()]));
+    }
+
+    #[test]
+    fn test_help_and_version() {
+        assert!(parse(&["-h"]).unwrap().help);
+        assert!(parse(&["--version"]).unwrap().version);
+    }
+
+    #[test]
+    fn test_print_last_dir() {
+        assert!(parse(&["--print-last-dir"]).unwrap().print_last_dir)
+    }
+
+    #[test]
+    fn test_chooser() {
+        let args = parse(&["--choose-files", "-"]).unwrap();
+        assert_eq!(args.chooser(), Some((Choose::Files, Path::new("-"))));
+
+        let args = parse(&["--choose-dir=/tmp/dir"]).unwrap();
+        assert_eq!(args.chooser(), Some((Choose::Dir, Path::new("/tmp/dir"))));
+
+        assert!(parse(&["--choose-files", "-", "--choose-dir", "-"]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_option() {
+        assert!(parse(&["--nope"]).is_err())
+    }
+}
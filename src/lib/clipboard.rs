@@ -0,0 +1,202 @@
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context};
+
+/// Files yanked or cut, waiting to be pasted
+#[derive(Debug, Default)]
+pub struct Clipboard {
+    pub files: Vec<PathBuf>,
+    // Cut files are moved when pasted instead of copied
+    pub cut: bool,
+}
+
+impl Clipboard {
+    /// Replaces the contents of the clipboard
+    pub fn yank(&mut self, files: Vec<PathBuf>, cut: bool) {
+        self.files = files;
+        self.cut = cut;
+    }
+
+    /// Copies or moves the files into dir, names that are taken get a
+    /// `.~1~` style suffix. Returns the paths of the pasted files
+    pub fn paste(&mut self, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let (pasted, result) = transfer(&self.files, dir, self.cut);
+
+        // Cut files only exist once, even when pasting stopped partway
+        if self.cut {
+            self.files.drain(..pasted.len());
+        }
+
+        result.map(|_| pasted)
+    }
+}
+
+/// Copies or moves files, directories included, into dir like pasting
+/// them. Stops at the first file that fails, returning the paths the
+/// ones before it ended up at and why it failed. Moving a file into
+/// the directory it's already in leaves it where it is
+pub fn transfer(files: &[PathBuf], dir: &Path, cut: bool) -> (Vec<PathBuf>, anyhow::Result<()>) {
+    let mut pasted = Vec::with_capacity(files.len());
+
+    for from in files {
+        match transfer_one(from, dir, cut) {
+            Ok(to) => pasted.push(to),
+            Err(e) => return (pasted, Err(e)),
+        }
+    }
+
+    (pasted, Ok(()))
+}
+
+fn transfer_one(from: &Path, dir: &Path, cut: bool) -> anyhow::Result<PathBuf> {
+    if dir.starts_with(from) {
+        bail!("Can't paste {} into itself", from.display())
+    }
+    if cut && from.parent() == Some(dir) {
+        return Ok(from.to_owned())
+    }
+
+    let name = from.file_name().context("Can't paste /")?;
+    let to = free_path(&dir.join(name));
+
+    if cut {
+        move_path(from, &to)
+    } else {
+        copy_path(from, &to)
+    }
+    .with_context(|| format!("Can't paste {}", from.display()))?;
+
+    Ok(to)
+}
+
+/// Returns path, or path with the first free `.~n~` suffix if it's taken
+fn free_path(path: &Path) -> PathBuf {
+    let mut free = path.to_owned();
+    let mut n = 1;
+    while free.symlink_metadata().is_ok() {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".~{}~", n));
+        free = PathBuf::from(name);
+        n += 1;
+    }
+
+    free
+}
+
+/// Copies a file, symlink or directory and everything in it
+fn copy_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    let ftype = from.symlink_metadata()?.file_type();
+
+    if ftype.is_symlink() {
+        std::os::unix::fs::symlink(std::fs::read_link(from)?, to)
+    } else if ftype.is_dir() {
+        std::fs::create_dir(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        Ok(())
+    } else {
+        std::fs::copy(from, to).map(|_| ())
+    }
+}
+
+/// Renames the file, copying then removing it across filesystems
+fn move_path(from: &Path, to: &Path) -> std::io::Result<()> {
+    match std::fs::rename(from, to) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {
+            copy_path(from, to)?;
+            match from.symlink_metadata()?.is_dir() {
+                true => std::fs::remove_dir_all(from),
+                false => std::fs::remove_file(from),
+            }
+        },
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Clipboard;
+    use std::path::PathBuf;
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fm-clipboard-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(dir.join("from/sub")).unwrap();
+        std::fs::create_dir_all(dir.join("to")).unwrap();
+        std::fs::write(dir.join("from/file"), "hi").unwrap();
+        std::fs::write(dir.join("from/sub/inner"), "").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_copy() {
+        let dir = testing_dir("copy");
+        let mut clipboard = Clipboard::default();
+        clipboard.yank(vec![dir.join("from/file"), dir.join("from/sub")], false);
+
+        clipboard.paste(&dir.join("to")).unwrap();
+        assert!(dir.join("to/sub/inner").exists());
+        assert!(dir.join("from/sub/inner").exists());
+
+        // Pasting again doesn't overwrite anything
+        let pasted = clipboard.paste(&dir.join("to")).unwrap();
+        assert_eq!(pasted[0], dir.join("to/file.~1~"));
+        assert_eq!(std::fs::read_to_string(&pasted[0]).unwrap(), "hi");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cut() {
+        let dir = testing_dir("cut");
+        let mut clipboard = Clipboard::default();
+        clipboard.yank(vec![dir.join("from/sub")], true);
+
+        clipboard.paste(&dir.join("to")).unwrap();
+        assert!(dir.join("to/sub/inner").exists());
+        assert!(!dir.join("from/sub").exists());
+        assert!(clipboard.files.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_paste_into_itself() {
+        let dir = testing_dir("itself");
+        let mut clipboard = Clipboard::default();
+        clipboard.yank(vec![dir.join("from")], false);
+        assert!(clipboard.paste(&dir.join("from/sub")).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cut_stops_partway() {
+        let dir = testing_dir("partway");
+        let mut clipboard = Clipboard::default();
+        let missing = dir.join("from/missing");
+        clipboard.yank(vec![dir.join("from/file"), missing.clone(), dir.join("from/sub")], true);
+
+        assert!(clipboard.paste(&dir.join("to")).is_err());
+        assert!(dir.join("to/file").exists());
+        // Only the files that weren't moved are left to paste
+        assert_eq!(clipboard.files, vec![missing, dir.join("from/sub")]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cut_into_same_dir() {
+        let dir = testing_dir("same-dir");
+        let mut clipboard = Clipboard::default();
+        clipboard.yank(vec![dir.join("from/file")], true);
+
+        assert_eq!(clipboard.paste(&dir.join("from")).unwrap(), vec![dir.join("from/file")]);
+        assert!(!dir.join("from/file.~1~").exists());
+        assert!(clipboard.files.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
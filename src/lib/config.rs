@@ -0,0 +1,317 @@
+use std::path::{Path, PathBuf};
+
+use super::action::Action;
+use super::sort::Sort;
+use super::filter::Filter;
+use super::long::Column;
+
+use anyhow::{anyhow, Context};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// User configuration read from `$XDG_CONFIG_HOME/fm/config.yml`
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub commands: Vec<Command>,
+    /// How many files to keep between the cursor and the edges of the list
+    pub scrolloff: usize,
+    /// Width ratios of the parent, current and preview columns,
+    /// a ratio of 0 hides that column
+    pub columns: [u16; 3],
+    /// How files are sorted until it's changed
+    pub sort: Sort,
+    /// Whether dotfiles are listed until they're toggled
+    pub show_hidden: bool,
+    /// Globs of files that are never listed
+    pub ignore: Vec<String>,
+    /// Whether files ignored by git are hidden
+    pub gitignore: bool,
+    /// Whether the long listing is shown until it's toggled
+    pub long: bool,
+    /// Columns of the long listing in the order they're shown
+    pub long_columns: Vec<Column>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            commands: Vec::new(),
+            scrolloff: 0,
+            columns: [1, 3, 4],
+            sort: Sort::default(),
+            show_hidden: true,
+            ignore: Vec::new(),
+            gitignore: false,
+            long: false,
+            long_columns: Column::defaults(),
+        }
+    }
+}
+
+/// A named command defined in the config, either a shell
+/// snippet or a sequence of builtin actions
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Command {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub key: Option<String>,
+    pub shell: Option<String>,
+    pub actions: Option<Vec<Action>>,
+}
+
+impl Command {
+    /// The actions to queue when the command is run
+    pub fn actions(&self) -> Vec<Action> {
+        match (&self.shell, &self.actions) {
+            (Some(cmd), _) => vec![Action::Shell(cmd.clone())],
+            (None, Some(actions)) => actions.clone(),
+            (None, None) => vec![],
+        }
+    }
+
+    /// The keys bound to this command if it's bound to a sequence like `gx`
+    pub fn sequence(&self) -> Option<&str> {
+        self.key.as_deref().and_then(parse_sequence)
+    }
+
+    /// Returns true if the key pressed is bound to this command
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        match self.key.as_deref().and_then(parse_key) {
+            Some(bind) => {
+                bind.code == key.code
+                    && bind.modifiers.contains(KeyModifiers::CONTROL)
+                        == key.modifiers.contains(KeyModifiers::CONTROL)
+            }
+            None => false,
+        }
+    }
+}
+
+impl Config {
+    /// Default directory holding the config file and plugins
+    pub fn default_dir() -> Option<PathBuf> {
+        let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+
+        Some(dir.join("fm"))
+    }
+
+    /// Default location of the config file
+    pub fn default_path() -> Option<PathBuf> {
+        Self::default_dir().map(|dir| dir.join("config.yml"))
+    }
+
+    /// Loads the config at path, a missing file gives the default config
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Error reading {}", path.display()))?;
+
+        Self::parse(&contents).with_context(|| format!("Error in {}", path.display()))
+    }
+
+    /// Parses and validates the contents of a config file
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        if contents.trim().is_empty() {
+            return Ok(Self::default())
+        }
+
+        let config: Self = serde_yaml::from_str(contents)?;
+
+        if config.columns[1] == 0 {
+            return Err(anyhow!("The current directory's column can't be hidden"))
+        }
+
+        for cmd in &config.commands {
+            match (&cmd.shell, &cmd.actions) {
+                (Some(_), Some(_)) => return Err(anyhow!("Command {} has both shell and actions", cmd.name)),
+                (None, None) => return Err(anyhow!("Command {} needs shell or actions", cmd.name)),
+                _ => {}
+            }
+
+            if let Some(key) = &cmd.key {
+                if !is_valid_key(key) {
+                    return Err(anyhow!("Command {} has an invalid key: {}", cmd.name, key))
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the command bound to the key pressed if there is one
+    pub fn binding(&self, key: &KeyEvent) -> Option<&Command> {
+        self.commands.iter().find(|c| c.matches(key))
+    }
+
+    /// Which files are listed when fm starts
+    pub fn filter(&self) -> Filter {
+        Filter {
+            show_hidden: self.show_hidden,
+            ignore: self.ignore.clone(),
+            gitignore: self.gitignore,
+        }
+    }
+
+    /// Returns the command with the given name if there is one
+    pub fn command(&self, name: &str) -> Option<&Command> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+}
+
+/// Parses a key such as `x`, `ctrl-x`, `enter` or `space`
+pub fn parse_key(key: &str) -> Option<KeyEvent> {
+    let (modifiers, key) = match key.strip_prefix("ctrl-") {
+        Some(rest) => (KeyModifiers::CONTROL, rest),
+        None => (KeyModifiers::NONE, key),
+    };
+
+    let code = match key {
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "space" => KeyCode::Char(' '),
+        "backspace" => KeyCode::Backspace,
+        _ => {
+            let mut chars = key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => KeyCode::Char(c),
+                _ => return None,
+            }
+        }
+    };
+
+    Some(KeyEvent { code, modifiers })
+}
+
+/// Parses a sequence of plain keys typed one after another such as `gx`,
+/// digits can't start one since they're taken as a count
+pub fn parse_sequence(keys: &str) -> Option<&str> {
+    let plain = |c: char| !c.is_whitespace() && !c.is_control();
+    let valid = keys.chars().count() > 1
+        && parse_key(keys).is_none()
+        && !keys.starts_with("ctrl-")
+        && !keys.starts_with(|c: char| c.is_ascii_digit())
+        && keys.chars().all(plain);
+
+    valid.then_some(keys)
+}
+
+/// Returns true if a command can be bound to key, a single key or a sequence
+pub fn is_valid_key(key: &str) -> bool {
+    parse_key(key).is_some() || parse_sequence(key).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_key, parse_sequence, Config};
+    use crate::action::Action;
+    use crate::sort::{Sort, SortBy};
+    use crate::long::Column;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    const CONFIG: &str = "
+commands:
+  - name: code
+    description: Open in VS Code
+    key: C
+    shell: code \"$f\"
+  - name: first
+    key: ctrl-t
+    actions: [top, select]
+";
+
+    #[test]
+    fn test_parse_commands() {
+        let config = Config::parse(CONFIG).unwrap();
+        assert_eq!(config.commands.len(), 2);
+        assert_eq!(config.command("code").unwrap().actions(), vec![Action::Shell("code \"$f\"".to_string())]);
+        assert_eq!(config.command("first").unwrap().actions(), vec![Action::Top, Action::Select]);
+    }
+
+    #[test]
+    fn test_binding() {
+        let config = Config::parse(CONFIG).unwrap();
+        let key = KeyEvent::new(KeyCode::Char('t'), KeyModifiers::CONTROL);
+        assert_eq!(config.binding(&key).unwrap().name, "first");
+
+        let key = KeyEvent::new(KeyCode::Char('t'), KeyModifiers::NONE);
+        assert!(config.binding(&key).is_none());
+    }
+
+    #[test]
+    fn test_empty_config() {
+        assert!(Config::parse("").unwrap().commands.is_empty())
+    }
+
+    #[test]
+    fn test_scrolloff() {
+        assert_eq!(Config::parse("scrolloff: 5").unwrap().scrolloff, 5);
+        assert_eq!(Config::parse("").unwrap().scrolloff, 0);
+    }
+
+    #[test]
+    fn test_columns() {
+        assert_eq!(Config::parse("columns: [0, 2, 3]").unwrap().columns, [0, 2, 3]);
+        assert_eq!(Config::parse("").unwrap().columns, [1, 3, 4]);
+        assert!(Config::parse("columns: [1, 0, 1]").is_err());
+        assert!(Config::parse("columns: [1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_sort() {
+        let config = Config::parse("sort:\n  by: modified\n  dirs_first: true").unwrap();
+        assert_eq!(config.sort, Sort { by: SortBy::Modified, reverse: false, dirs_first: true });
+        assert!(Config::parse("sort:\n  by: colour").is_err());
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = Config::parse("show_hidden: false\nignore: ['*.pyc', target/]").unwrap().filter();
+        assert!(!filter.show_hidden && !filter.gitignore);
+        assert_eq!(filter.ignore, vec!["*.pyc", "target/"]);
+        assert!(Config::parse("").unwrap().filter().show_hidden);
+    }
+
+    #[test]
+    fn test_long_columns() {
+        let config = Config::parse("long: true\nlong_columns: [size, modified]").unwrap();
+        assert!(config.long);
+        assert_eq!(config.long_columns, vec![Column::Size, Column::Modified]);
+        assert_eq!(Config::parse("").unwrap().long_columns, Column::defaults());
+        assert!(Config::parse("long_columns: [inode]").is_err());
+    }
+
+    #[test]
+    fn test_needs_shell_or_actions() {
+        assert!(Config::parse("commands:\n  - name: nothing\n").is_err())
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(parse_key("x"), Some(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE)));
+        assert_eq!(parse_key("ctrl-e"), Some(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::CONTROL)));
+        assert_eq!(parse_key("enter"), Some(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)));
+        assert_eq!(parse_key("xy"), None);
+    }
+
+    #[test]
+    fn test_parse_sequence() {
+        assert_eq!(parse_sequence("gx"), Some("gx"));
+        assert_eq!(parse_sequence("x"), None);
+        assert_eq!(parse_sequence("enter"), None);
+        assert_eq!(parse_sequence("ctrl-xy"), None);
+        assert_eq!(parse_sequence("2g"), None);
+        assert_eq!(parse_sequence("g x"), None);
+        assert!(Config::parse("commands:\n  - name: gx\n    key: gx\n    actions: [top]").is_ok());
+    }
+}
@@ -5,7 +5,9 @@ use tui::style::Color;
 
 use std::convert::From;
 use std::os::unix::prelude::{MetadataExt, OsStrExt};
+use std::fs::Metadata;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug, Clone)]
 pub struct File {
@@ -14,16 +16,28 @@ pub struct File {
     pub perms: Permissions,
     pub path: PathBuf,
     pub size: u64,
-    pub is_selected: bool,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    // When the file's metadata last changed
+    pub changed: Option<SystemTime>,
+    // When the file was created, or last changed where that isn't known
+    pub created: Option<SystemTime>,
+    pub uid: u32,
+    pub gid: u32,
+    // How many hard links point to the file
+    pub links: u64,
+    pub inode: u64,
+    // The device the file is on
+    pub device: u64,
+    // Where the file points to if it's a symlink
+    pub target: Option<PathBuf>,
+    // How deep the file is in the tree, 0 for files in the cwd
+    pub depth: usize,
 }
 
 impl File {
     /// Gets a color based on the FileType of the File
     pub fn color(&self) -> Color {
-        if self.is_selected {
-            return Color::Rgb(213, 0, 255)
-        }
-
         match self.ftype {
             FileType::Directory => Color::LightBlue,
             FileType::File => Color::White,
@@ -41,13 +55,13 @@ impl File {
     pub fn size_to_readable(&self) -> String {
         let size = self.size;
         if size < 1000 {
-            return format!("{} B", size)
+            format!("{} B", size)
         } else {
             let mut iter = IntoIterator::into_iter(['k', 'M', 'G', 'T', 'E'])
                 .enumerate()
                 .skip_while(|(i, _)| size / ((*i + 1) * 1000) as u64 > 999_950);
             let (i, ch) = iter.next().unwrap();
-            return format!("{:.2} {}B", size / ((i + 1) * 1000) as u64, ch)
+            format!("{:.2} {}B", size / ((i + 1) * 1000) as u64, ch)
         }
     }
 
@@ -64,15 +78,9 @@ impl File {
 impl From<std::path::PathBuf> for File {
     fn from(path: std::path::PathBuf) -> Self {
         let name = String::from_utf8(path.file_name().unwrap().as_bytes().to_vec()).unwrap();
-        let mdata = path.metadata();
-        let (perms, size, ftype) = match mdata {
-            Ok(mdata) => {
-                (Permissions::from(mdata.mode()), mdata.size(), Some(mdata.file_type()))
-            }
-            Err(_) => (Permissions::from(u32::MAX), 0, None)
-        };
+        let mdata = path.metadata().ok();
 
-        let ftype = match ftype {
+        let ftype = match mdata.as_ref().map(|m| m.file_type()) {
             Some(f) => {
                 if f.is_dir() {
                     FileType::Directory
@@ -86,9 +94,8 @@ impl From<std::path::PathBuf> for File {
             None => FileType::File
         };
 
-        let is_selected = false;
-
-        Self { name, ftype, perms, path, size, is_selected }
+        let target = std::fs::read_link(&path).ok();
+        Self { target, ..File::with_metadata(name, path, ftype, mdata.as_ref()) }
     }
 }
 
@@ -96,11 +103,10 @@ impl From<std::fs::DirEntry> for File {
     fn from(entry: std::fs::DirEntry) -> File {
         let name = String::from_utf8(entry.file_name().as_bytes().to_vec()).unwrap();
         let path = entry.path();
-        let (perms, size) = match entry.metadata() {
-            Ok(mdata) => {
-                (Permissions::from(mdata.mode()), mdata.size())
-            }
-            Err(_) => (Permissions::from(u32::MAX), 0)
+        let mdata = entry.metadata().ok();
+        let perms = match &mdata {
+            Some(mdata) => Permissions::from(mdata.mode()),
+            None => Permissions::from(u32::MAX),
         };
 
         let ftype = match entry.file_type() {
@@ -118,9 +124,55 @@ impl From<std::fs::DirEntry> for File {
             Err(..) => FileType::File
         };
 
-        let is_selected = false;
+        let file = File::with_metadata(name, path, ftype, mdata.as_ref());
+        match ftype {
+            FileType::Symlink => Self { target: std::fs::read_link(&file.path).ok(), ..file },
+            _ => file,
+        }
+    }
+}
+
+impl File {
+    /// A file with everything else taken from its metadata, or
+    /// left empty when it couldn't be read
+    fn with_metadata(name: String, path: PathBuf, ftype: FileType, mdata: Option<&Metadata>) -> Self {
+        let mdata = match mdata {
+            Some(mdata) => mdata,
+            None => return Self {
+                name, ftype, path,
+                perms: Permissions::from(u32::MAX),
+                size: 0,
+                modified: None,
+                accessed: None,
+                changed: None,
+                created: None,
+                uid: 0,
+                gid: 0,
+                links: 0,
+                inode: 0,
+                device: 0,
+                target: None,
+                depth: 0,
+            },
+        };
 
-        Self { name, ftype, perms, path, size, is_selected }
+        let changed = SystemTime::UNIX_EPOCH + Duration::new(mdata.ctime().max(0) as u64, mdata.ctime_nsec() as u32);
+        Self {
+            name, ftype, path,
+            perms: Permissions::from(mdata.mode()),
+            size: mdata.size(),
+            modified: mdata.modified().ok(),
+            accessed: mdata.accessed().ok(),
+            changed: Some(changed),
+            created: Some(mdata.created().unwrap_or(changed)),
+            uid: mdata.uid(),
+            gid: mdata.gid(),
+            links: mdata.nlink(),
+            inode: mdata.ino(),
+            device: mdata.dev(),
+            target: None,
+            depth: 0,
+        }
     }
 }
 
@@ -0,0 +1,265 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::file::File;
+use super::filetype::FileType;
+use super::glob;
+
+/// Decides which files are listed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    /// Whether files starting with a dot are listed
+    pub show_hidden: bool,
+    /// Globs matched against file names, ones ending in `/` only match directories
+    pub ignore: Vec<String>,
+    /// Whether files ignored by git in the enclosing repository are hidden
+    pub gitignore: bool,
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self { show_hidden: true, ignore: Vec::new(), gitignore: false }
+    }
+}
+
+/// The rules of each directory's `.gitignore` by directory, so listing
+/// a directory again doesn't reread every `.gitignore` above it.
+/// Clones share what's been read
+#[derive(Debug, Clone, Default)]
+pub struct GitignoreCache(Rc<RefCell<HashMap<PathBuf, Rc<Vec<Rule>>>>>);
+
+impl GitignoreCache {
+    /// Forgets the `.gitignore` files read so far, for when they may have changed
+    pub fn forget(&self) {
+        self.0.borrow_mut().clear();
+    }
+
+    /// The rules of the `.gitignore` in dir, read the first time it's asked for
+    fn rules(&self, dir: &Path) -> Rc<Vec<Rule>> {
+        if let Some(rules) = self.0.borrow().get(dir) {
+            return Rc::clone(rules)
+        }
+
+        let rules = match std::fs::read_to_string(dir.join(".gitignore")) {
+            Ok(contents) => contents.lines().filter_map(|line| Rule::parse(dir, line)).collect(),
+            Err(_) => Vec::new(),
+        };
+        let rules = Rc::new(rules);
+        self.0.borrow_mut().insert(dir.to_owned(), Rc::clone(&rules));
+        rules
+    }
+}
+
+impl Filter {
+    /// Removes the files in dir that shouldn't be listed, reading
+    /// `.gitignore` files through gitignores
+    pub fn apply(&self, dir: &Path, files: &mut Vec<File>, gitignores: &GitignoreCache) {
+        let gitignore = match self.gitignore {
+            true => Gitignore::new(dir, gitignores),
+            false => None,
+        };
+
+        files.retain(|file| {
+            let is_dir = file.ftype == FileType::Directory;
+            let hidden = !self.show_hidden && file.name.starts_with('.');
+            let ignored = self.ignore.iter().any(|pattern| match pattern.strip_suffix('/') {
+                Some(pattern) => is_dir && glob::matches(pattern, &file.name),
+                None => glob::matches(pattern, &file.name),
+            });
+            let gitignored = gitignore.as_ref().is_some_and(|g| g.ignores(&file.path, is_dir));
+
+            !(hidden || ignored || gitignored)
+        })
+    }
+}
+
+/// The `.gitignore` rules that apply in a directory of a git repository,
+/// from the root of the repository down to the directory
+struct Gitignore {
+    rules: Vec<Rc<Vec<Rule>>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Rule {
+    // The directory of the .gitignore the rule is from
+    base: PathBuf,
+    pattern: String,
+    negated: bool,
+    dir_only: bool,
+    // Patterns with a slash match the path from base instead of the name
+    anchored: bool,
+}
+
+impl Gitignore {
+    /// The rules for dir, or None if it isn't in a repository
+    fn new(dir: &Path, cache: &GitignoreCache) -> Option<Self> {
+        let root = dir.ancestors().find(|d| d.join(".git").exists())?;
+        let dirs = dir.ancestors().take_while(|d| *d != root).chain([root]).collect::<Vec<_>>();
+
+        // Files further down take priority so they're read last
+        let rules = dirs.into_iter().rev().map(|base| cache.rules(base)).collect();
+        Some(Self { rules })
+    }
+
+    fn ignores(&self, path: &Path, is_dir: bool) -> bool {
+        // git never lists its own directory
+        if path.file_name().is_some_and(|name| name == ".git") {
+            return true
+        }
+
+        let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+        let mut ignored = false;
+        for rule in self.rules.iter().flat_map(|rules| rules.iter()) {
+            if rule.dir_only && !is_dir {
+                continue
+            }
+
+            let matched = match rule.anchored {
+                true => path.strip_prefix(&rule.base).is_ok_and(|rel| {
+                    let rel = rel.to_string_lossy();
+                    let pattern = rule.pattern.split('/').collect::<Vec<_>>();
+                    matches_path(&pattern, &rel.split('/').collect::<Vec<_>>())
+                }),
+                false => glob::matches(&rule.pattern, &name),
+            };
+
+            // The last rule that matches wins
+            if matched {
+                ignored = !rule.negated;
+            }
+        }
+
+        ignored
+    }
+}
+
+/// Matches a path a directory at a time so `*` stays within one
+/// directory, while `**` matches any number of them
+fn matches_path(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| matches_path(rest, &path[skip..])),
+        Some((first, rest)) => path
+            .split_first()
+            .is_some_and(|(name, path)| glob::matches(first, name) && matches_path(rest, path)),
+    }
+}
+
+impl Rule {
+    fn parse(base: &Path, line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+
+        // `**/name` matches at any depth like a plain name
+        let line = line.strip_prefix("**/").unwrap_or(line);
+        let anchored = line.contains('/');
+        let pattern = line.strip_prefix('/').unwrap_or(line).to_string();
+
+        Some(Self { base: base.to_owned(), pattern, negated, dir_only, anchored })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{matches_path, Filter};
+    use crate::sort::Sort;
+    use crate::workingdir::WorkingDir;
+    use std::path::{Path, PathBuf};
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fm-filter-{}-{}", name, std::process::id()));
+        for sub in [".git", "target", "src/build", "docs"] {
+            std::fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for file in [".env", "main.pyc", "src/keep.log", "src/drop.log", "docs/target"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        std::fs::write(dir.join(".gitignore"), "/target/\n*.log\n!keep.log\n").unwrap();
+        std::fs::write(dir.join("src/.gitignore"), "build\n").unwrap();
+        dir
+    }
+
+    fn names(dir: &Path, filter: &Filter) -> Vec<String> {
+        listed(&WorkingDir::new(Some(dir), filter.clone(), Sort::default()).unwrap())
+    }
+
+    fn listed(wd: &WorkingDir) -> Vec<String> {
+        let mut names = wd.files().iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_hidden_and_ignore() {
+        let dir = testing_dir("ignore");
+        let filter = Filter { show_hidden: false, ignore: vec!["*.pyc".into(), "docs/".into()], ..Filter::default() };
+        assert_eq!(names(&dir, &filter), vec!["src", "target"]);
+
+        // Only directories match patterns ending in a slash
+        let filter = Filter { ignore: vec!["target/".into()], ..Filter::default() };
+        assert_eq!(names(&dir.join("docs"), &filter), vec!["target"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_gitignore() {
+        let dir = testing_dir("git");
+        let filter = Filter { gitignore: true, ..Filter::default() };
+        assert_eq!(names(&dir, &filter), vec![".env", ".gitignore", "docs", "main.pyc", "src"]);
+        assert_eq!(names(&dir.join("src"), &filter), vec![".gitignore", "keep.log"]);
+        // Anchored patterns only match from their own directory
+        assert_eq!(names(&dir.join("docs"), &filter), vec!["target"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_anchored_star() {
+        let dir = testing_dir("anchored");
+        std::fs::create_dir_all(dir.join("src/build/deep")).unwrap();
+        std::fs::write(dir.join("src/build/deep/a.log"), "").unwrap();
+        std::fs::write(dir.join(".gitignore"), "src/*/a.log\n").unwrap();
+        let filter = Filter { gitignore: true, ..Filter::default() };
+        assert_eq!(names(&dir.join("src/build/deep"), &filter), vec!["a.log"]);
+
+        assert!(matches_path(&["src", "*.log"], &["src", "a.log"]));
+        assert!(!matches_path(&["src", "*"], &["src", "a", "b"]));
+        assert!(matches_path(&["a", "**", "b"], &["a", "b"]));
+        assert!(matches_path(&["a", "**", "b"], &["a", "x", "y", "b"]));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_gitignore_cache() {
+        let dir = testing_dir("cache");
+        let filter = Filter { gitignore: true, ..Filter::default() };
+        let mut wd = WorkingDir::new(Some(&dir.join("src")), filter, Sort::default()).unwrap();
+        assert_eq!(listed(&wd), vec![".gitignore", "keep.log"]);
+
+        // Changes only show once the cache is forgotten, even in other tabs
+        std::fs::write(dir.join("src/.gitignore"), "").unwrap();
+        wd.update().unwrap();
+        assert_eq!(listed(&wd), vec![".gitignore", "keep.log"]);
+        assert_eq!(listed(&wd.at(wd.cwd()).unwrap()), vec![".gitignore", "keep.log"]);
+        wd.forget_gitignores();
+        wd.update().unwrap();
+        assert_eq!(listed(&wd), vec![".gitignore", "build", "keep.log"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+/// Matches a file name against a shell style glob. `*` matches anything,
+/// `?` matches one character, `[abc]`, `[a-z]` and `[!abc]` match one
+/// character from a set and `\` matches the next character literally
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    let (mut p, mut n) = (0, 0);
+    // Where to go back to when what follows the last `*` doesn't match
+    let mut star: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+                continue
+            },
+            Some('?') => Some(1),
+            Some('[') => match_class(&pattern[p..], name[n]),
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == name[n]).then_some(2),
+            Some(&c) => (c == name[n]).then_some(1),
+            None => None,
+        };
+
+        match (step, star) {
+            (Some(step), _) => {
+                p += step;
+                n += 1;
+            },
+            // Let the `*` swallow one more character
+            (None, Some((star_p, star_n))) => {
+                star = Some((star_p, star_n + 1));
+                p = star_p + 1;
+                n = star_n + 1;
+            },
+            (None, None) => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches c against the `[...]` class at the start of pattern, returning
+/// how long the class is if it matches. An unclosed `[` matches itself
+fn match_class(pattern: &[char], c: char) -> Option<usize> {
+    let end = match pattern.iter().skip(2).position(|&p| p == ']') {
+        Some(i) => i + 2,
+        None => return (c == '[').then_some(1),
+    };
+
+    let (negated, set) = match pattern[1] {
+        '!' | '^' => (true, &pattern[2..end]),
+        _ => (false, &pattern[1..end]),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            found |= (set[i]..=set[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= set[i] == c;
+            i += 1;
+        }
+    }
+
+    (found != negated).then_some(end + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn test_star() {
+        assert!(matches("*.log", "build.log"));
+        assert!(matches("*.log", ".log"));
+        assert!(!matches("*.log", "build.log.old"));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn test_question_mark() {
+        assert!(matches("file?.txt", "file1.txt"));
+        assert!(!matches("file?.txt", "file.txt"));
+    }
+
+    #[test]
+    fn test_class() {
+        assert!(matches("[abc]*", "apple"));
+        assert!(!matches("[!abc]*", "apple"));
+        assert!(matches("img[0-9].png", "img7.png"));
+        assert!(!matches("img[0-9].png", "imgx.png"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("a[", "a["));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+    }
+}
@@ -0,0 +1,205 @@
+use super::action::Action;
+use super::app::InputMode;
+use super::config::Config;
+use super::sort::SortBy;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Keys only bound as a sequence. Like vim operators are doubled in
+/// Normal mode, in Visual mode they apply to the range with one key
+const SEQUENCES: &[(&str, Action, bool)] = &[
+    ("gg", Action::Top, true),
+    ("dd", Action::Delete, false),
+    ("yy", Action::Yank, false),
+    ("gn", Action::NewTab, false),
+    ("gc", Action::CloseTab, false),
+    ("gt", Action::NextTab, false),
+    ("gT", Action::PrevTab, false),
+    ("zl", Action::LongListing, false),
+    ("zt", Action::Tree, false),
+    ("zo", Action::Expand, false),
+    ("zc", Action::Collapse, false),
+    ("zh", Action::ToggleHidden, false),
+    ("zi", Action::ToggleGitignore, false),
+    ("on", Action::Sort(SortBy::Natural), false),
+    ("os", Action::Sort(SortBy::Size), false),
+    ("om", Action::Sort(SortBy::Modified), false),
+    ("oc", Action::Sort(SortBy::Created), false),
+    ("oe", Action::Sort(SortBy::Extension), false),
+    ("ot", Action::Sort(SortBy::Type), false),
+    ("or", Action::SortReverse, false),
+    ("od", Action::SortDirsFirst, false),
+];
+
+/// Buffers the keys pressed in Normal and Visual mode until they
+/// make up a binding, optionally prefixed with a count like `5j`
+#[derive(Debug, Default)]
+pub struct KeyParser {
+    count: Option<usize>,
+    keys: String,
+}
+
+impl KeyParser {
+    /// Takes the next key pressed, returning the actions it's bound to along
+    /// with the count once the keys make up a binding. Keys that can't
+    /// become a binding anymore are dropped
+    pub fn feed(&mut self, key: &KeyEvent, mode: InputMode, config: &Config) -> Option<(Vec<Action>, Option<usize>)> {
+        // Escape cancels whatever is pending
+        if key.code == KeyCode::Esc && !self.pending().is_empty() {
+            self.clear();
+            return None
+        }
+
+        let ch = match key.code {
+            KeyCode::Char(ch) if !key.modifiers.contains(KeyModifiers::CONTROL) => Some(ch),
+            _ => None,
+        };
+
+        // Digits build up the count, a lone 0 isn't a count
+        if let Some(digit) = ch.and_then(|c| c.to_digit(10)) {
+            if self.keys.is_empty() && (digit != 0 || self.count.is_some()) {
+                let count = self.count.unwrap_or(0).saturating_mul(10).saturating_add(digit as usize);
+                self.count = Some(count);
+                return None
+            }
+        }
+
+        let count = self.count.take();
+        let first = self.keys.is_empty();
+
+        // User defined commands can override the default keys
+        if first {
+            if let Some(cmd) = config.binding(key) {
+                return Some((cmd.actions(), count))
+            }
+        }
+
+        // Sequences bound in the config come first so they override ours
+        let bound = config.commands
+            .iter()
+            .filter_map(|cmd| Some((cmd.sequence()?, cmd.actions())));
+        let builtin = SEQUENCES
+            .iter()
+            .filter(|(_, _, visual)| mode != InputMode::Visual || *visual)
+            .map(|(seq, action, _)| (*seq, vec![action.clone()]));
+
+        if let Some(ch) = ch {
+            self.keys.push(ch);
+            let mut prefix = false;
+            for (seq, actions) in bound.chain(builtin) {
+                if seq == self.keys {
+                    self.keys.clear();
+                    return Some((actions, count))
+                }
+                prefix |= seq.starts_with(&self.keys);
+            }
+
+            if prefix {
+                self.count = count;
+                return None
+            }
+        }
+
+        self.keys.clear();
+        match first {
+            true => Action::from_key(key).map(|action| (vec![action], count)),
+            // The sequence can't be finished but the last key may still
+            // mean something on its own with the count, like `j` in `5dj`
+            false => {
+                self.count = count;
+                self.feed(key, mode, config)
+            },
+        }
+    }
+
+    /// The count and keys typed so far
+    pub fn pending(&self) -> String {
+        match self.count {
+            Some(count) => format!("{}{}", count, self.keys),
+            None => self.keys.clone(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.count = None;
+        self.keys.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyParser;
+    use crate::action::Action;
+    use crate::app::InputMode;
+    use crate::config::Config;
+
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    fn feed(parser: &mut KeyParser, keys: &str, mode: InputMode) -> Option<(Vec<Action>, Option<usize>)> {
+        let config = Config::default();
+        keys.chars()
+            .map(|c| parser.feed(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), mode, &config))
+            .last()
+            .flatten()
+    }
+
+    #[test]
+    fn test_count() {
+        let mut parser = KeyParser::default();
+        assert_eq!(feed(&mut parser, "5j", InputMode::Normal), Some((vec![Action::Down], Some(5))));
+        assert_eq!(feed(&mut parser, "20G", InputMode::Normal), Some((vec![Action::Bottom], Some(20))));
+        assert_eq!(feed(&mut parser, "k", InputMode::Normal), Some((vec![Action::Up], None)));
+    }
+
+    #[test]
+    fn test_sequences() {
+        let mut parser = KeyParser::default();
+        assert_eq!(feed(&mut parser, "3d", InputMode::Normal), None);
+        assert_eq!(parser.pending(), "3d");
+        assert_eq!(feed(&mut parser, "d", InputMode::Normal), Some((vec![Action::Delete], Some(3))));
+        assert_eq!(parser.pending(), "");
+
+        assert_eq!(feed(&mut parser, "gg", InputMode::Normal), Some((vec![Action::Top], None)));
+        // The key that broke the sequence still runs
+        assert_eq!(feed(&mut parser, "dx", InputMode::Normal), Some((vec![Action::Cut], None)));
+        assert_eq!(feed(&mut parser, "5dj", InputMode::Normal), Some((vec![Action::Down], Some(5))));
+        assert_eq!(feed(&mut parser, "dg", InputMode::Normal), None);
+        assert_eq!(parser.pending(), "g");
+        assert_eq!(feed(&mut parser, "g", InputMode::Normal), Some((vec![Action::Top], None)));
+    }
+
+    #[test]
+    fn test_config_sequences() {
+        let config = Config::parse(
+            "commands:\n  - name: home\n    key: gh\n    actions: [top]\n  - name: mine\n    key: dd\n    actions: [select]",
+        ).unwrap();
+        let mut parser = KeyParser::default();
+        let mut feed = |keys: &str| keys.chars()
+            .map(|c| parser.feed(&KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE), InputMode::Normal, &config))
+            .last()
+            .flatten();
+
+        assert_eq!(feed("gh"), Some((vec![Action::Top], None)));
+        // Config sequences override the builtin ones
+        assert_eq!(feed("dd"), Some((vec![Action::Select], None)));
+        assert_eq!(feed("gg"), Some((vec![Action::Top], None)));
+    }
+
+    #[test]
+    fn test_visual_operators() {
+        let mut parser = KeyParser::default();
+        assert_eq!(feed(&mut parser, "d", InputMode::Visual), Some((vec![Action::Delete], None)));
+        assert_eq!(feed(&mut parser, "y", InputMode::Visual), Some((vec![Action::Yank], None)));
+    }
+
+    #[test]
+    fn test_escape_cancels() {
+        let mut parser = KeyParser::default();
+        let config = Config::default();
+        feed(&mut parser, "4", InputMode::Normal);
+        let esc = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(parser.feed(&esc, InputMode::Normal, &config), None);
+        assert_eq!(parser.pending(), "");
+        assert_eq!(parser.feed(&esc, InputMode::Normal, &config), Some((vec![Action::ClearSelection], None)));
+    }
+}
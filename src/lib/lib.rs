@@ -5,10 +5,27 @@ pub mod filetype;
 pub mod app;
 pub mod ui;
 pub mod userinput;
+pub mod action;
+pub mod config;
+pub mod plugin;
+pub mod args;
+pub mod remote;
+pub mod clipboard;
+pub mod glob;
+pub mod keys;
+pub mod watch;
+pub mod tab;
+pub mod sort;
+pub mod filter;
+pub mod long;
+pub mod time;
+pub mod users;
 
 pub use app::App;
 pub use app::InputMode;
 pub use file::File;
+pub use action::Action;
+pub use config::Config;
 
 #[cfg(test)]
 mod tests {
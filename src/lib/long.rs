@@ -0,0 +1,150 @@
+use super::file::File;
+use super::time;
+use super::users::Users;
+
+use serde::Deserialize;
+
+// Columns are dropped until the name has at least this much room
+const MIN_NAME: usize = 12;
+
+/// A column of the long listing, shown before the name except
+/// for the symlink target which follows it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Column {
+    Permissions,
+    /// The permissions in octal
+    Octal,
+    /// The number of hard links
+    Links,
+    Owner,
+    Group,
+    Size,
+    Modified,
+    /// Where symlinks point to
+    Target,
+}
+
+impl Column {
+    /// The columns `ls -l` shows
+    pub fn defaults() -> Vec<Column> {
+        use Column::*;
+        vec![Permissions, Links, Owner, Group, Size, Modified, Target]
+    }
+
+    fn value(&self, file: &File, users: &Users) -> String {
+        match self {
+            Column::Permissions => file.perms.to_string(),
+            Column::Octal => file.perms.octal(),
+            Column::Links => file.links.to_string(),
+            Column::Owner => users.user(file.uid),
+            Column::Group => users.group(file.gid),
+            Column::Size => file.size_to_readable(),
+            Column::Modified => file.modified.map(time::short).unwrap_or_else(|| String::from("?")),
+            Column::Target => file.target.as_ref()
+                .map(|target| format!(" -> {}", target.display()))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Numbers line up on the right
+    fn right_aligned(&self) -> bool {
+        matches!(self, Column::Links | Column::Size)
+    }
+}
+
+/// A file's line in the long listing without the name
+pub struct Line {
+    /// The columns before the name, padded to line up with the other lines
+    pub info: String,
+    /// What follows the name
+    pub target: String,
+}
+
+/// Lays out the columns for files so they line up in width. Columns are
+/// dropped from the right when there isn't room left for the names
+pub fn lines(files: &[File], columns: &[Column], users: &Users, width: usize) -> Vec<Line> {
+    let info = columns.iter().filter(|c| **c != Column::Target).collect::<Vec<_>>();
+    let values = info.iter()
+        .map(|column| files.iter().map(|f| column.value(f, users)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let widths = values.iter()
+        .map(|values| values.iter().map(|v| v.chars().count()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+
+    let mut shown = info.len();
+    while shown > 0 && widths[..shown].iter().map(|w| w + 1).sum::<usize>() + MIN_NAME > width {
+        shown -= 1;
+    }
+    let target = columns.contains(&Column::Target);
+
+    (0..files.len())
+        .map(|i| {
+            let mut line = String::new();
+            for c in 0..shown {
+                let value = &values[c][i];
+                match info[c].right_aligned() {
+                    true => line.push_str(&format!("{:>1$} ", value, widths[c])),
+                    false => line.push_str(&format!("{:<1$} ", value, widths[c])),
+                }
+            }
+
+            Line {
+                info: line,
+                target: if target { Column::Target.value(&files[i], users) } else { String::new() },
+            }
+        })
+        .collect()
+}
+
+/// Cuts s down to width characters, ending it with `…` when it's cut
+pub fn truncate(s: &str, width: usize) -> String {
+    match s.chars().count() > width {
+        true if width > 0 => s.chars().take(width - 1).chain(['…']).collect(),
+        true => String::new(),
+        false => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lines, truncate, Column};
+    use crate::file::File;
+    use crate::users::Users;
+    use std::path::PathBuf;
+
+    fn files() -> Vec<File> {
+        let mut small = File::from(PathBuf::from("small"));
+        small.size = 5;
+        small.links = 12;
+        let mut big = File::from(PathBuf::from("big"));
+        big.size = 123456;
+        big.links = 1;
+        big.target = Some(PathBuf::from("small"));
+        vec![small, big]
+    }
+
+    #[test]
+    fn test_aligned() {
+        let lines = lines(&files(), &[Column::Links, Column::Size, Column::Target], &Users::default(), 80);
+        assert_eq!(lines[0].info, "12    5 B ");
+        assert_eq!(lines[1].info, " 1 123 kB ");
+        assert_eq!(lines[0].target, "");
+        assert_eq!(lines[1].target, " -> small");
+    }
+
+    #[test]
+    fn test_narrow() {
+        // The size column doesn't fit next to the name
+        let lines = lines(&files(), &[Column::Links, Column::Size], &Users::default(), 20);
+        assert_eq!(lines[0].info, "12 ");
+        assert!(super::lines(&files(), &[Column::Links], &Users::default(), 10)[0].info.is_empty());
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("filename.txt", 6), "filen…");
+        assert_eq!(truncate("short", 6), "short");
+        assert_eq!(truncate("short", 0), "");
+    }
+}
@@ -1,36 +1,194 @@
 use std::convert::From;
 
+use super::users::Identity;
+
+// The file type bits of st_mode
+const TYPE_MASK: u32 = 0o170000;
+const SOCKET: u32 = 0o140000;
+const SYMLINK: u32 = 0o120000;
+const REGULAR: u32 = 0o100000;
+const BLOCK: u32 = 0o060000;
+const DIRECTORY: u32 = 0o040000;
+const CHAR: u32 = 0o020000;
+const FIFO: u32 = 0o010000;
+
+const SETUID: u32 = 0o4000;
+const SETGID: u32 = 0o2000;
+const STICKY: u32 = 0o1000;
+
+/// What can be done to a file, the values are the bits of each
+/// class in the mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read = 4,
+    Write = 2,
+    Execute = 1,
+}
+
+/// The whole `st_mode` of a file, its type as well as its permissions
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Permissions {
     perms: u32
 }
 
 impl Permissions {
-    /// Returns true if the user has executable perms
+    /// Returns true if the owner has executable perms
     pub fn is_user_exec(&self) -> bool {
-        self.perms & 1 == 1
+        self.perms & 0o100 != 0
     }
 
-    /// Returns true if the user has write perms
+    /// Returns true if the owner has write perms
     pub fn is_user_write(&self) -> bool {
-        (self.perms >> 1) & 1 == 1
+        self.perms & 0o200 != 0
     } 
 
-    /// Returns true if the user has read perms
+    /// Returns true if the owner has read perms
     pub fn is_user_read(&self) -> bool {
-        (self.perms >> 2) & 1 == 1
+        self.perms & 0o400 != 0
     } 
 
+    pub fn is_setuid(&self) -> bool {
+        self.perms & SETUID != 0
+    }
+
+    pub fn is_setgid(&self) -> bool {
+        self.perms & SETGID != 0
+    }
+
+    pub fn is_sticky(&self) -> bool {
+        self.perms & STICKY != 0
+    }
+
+    /// The permission bits including setuid, setgid and sticky
+    pub fn mode(&self) -> u32 {
+        self.perms & 0o7777
+    }
+
+    /// The mode in octal like `stat` shows it, e.g. `0755`
+    pub fn octal(&self) -> String {
+        format!("{:04o}", self.mode())
+    }
+
+    /// The character `ls` shows for the file type
+    pub fn type_char(&self) -> char {
+        match self.perms & TYPE_MASK {
+            REGULAR => '-',
+            DIRECTORY => 'd',
+            SYMLINK => 'l',
+            SOCKET => 's',
+            BLOCK => 'b',
+            CHAR => 'c',
+            FIFO => 'p',
+            _ => '?',
+        }
+    }
+
+    /// Returns true for directories
+    pub fn is_dir(&self) -> bool {
+        self.perms & TYPE_MASK == DIRECTORY
+    }
+
+    /// Applies a mode the way chmod takes it, either octal like `755` or
+    /// symbolic like `u+x,go-w`, giving None if it isn't a valid mode
+    pub fn apply(&self, spec: &str) -> Option<Permissions> {
+        if spec.is_empty() {
+            return Some(*self)
+        }
+
+        let mut mode = self.mode();
+        for clause in spec.split(',') {
+            if !clause.is_empty() && clause.chars().all(|c| c.is_digit(8)) {
+                if clause.len() > 4 {
+                    return None
+                }
+                mode = u32::from_str_radix(clause, 8).ok()?;
+                continue
+            }
+
+            // Who the clause is for then one or more operators with their permissions
+            let who_end = clause.find(|c| !"ugoa".contains(c)).unwrap_or(clause.len());
+            let (who, mut ops) = clause.split_at(who_end);
+            let mask = match who.is_empty() {
+                true => 0o7777,
+                false => who.chars().fold(0, |mask, c| mask | match c {
+                    'u' => 0o4700,
+                    'g' => 0o2070,
+                    'o' => 0o1007,
+                    _ => 0o7777,
+                }),
+            };
+            if ops.is_empty() {
+                return None
+            }
+
+            while let Some(op) = ops.chars().next() {
+                if !"+-=".contains(op) {
+                    return None
+                }
+                let rest = &ops[op.len_utf8()..];
+                let end = rest.find(|c| "+-=".contains(c)).unwrap_or(rest.len());
+                let (perms, next) = rest.split_at(end);
+
+                let mut bits = 0;
+                for c in perms.chars() {
+                    bits |= match c {
+                        'r' => 0o444,
+                        'w' => 0o222,
+                        'x' => 0o111,
+                        // Execute only for directories and files someone can execute
+                        'X' if self.is_dir() || mode & 0o111 != 0 => 0o111,
+                        'X' => 0,
+                        's' => SETUID | SETGID,
+                        't' => STICKY,
+                        _ => return None,
+                    }
+                }
+                bits &= mask;
+
+                match op {
+                    '+' => mode |= bits,
+                    '-' => mode &= !bits,
+                    _ => mode = (mode & !mask) | bits,
+                }
+                ops = next;
+            }
+        }
+
+        Some(Permissions::from((self.perms & !0o7777) | mode))
+    }
+
+    /// Returns true if user can access a file owned by uid and gid
+    /// with these permissions. Root can read and write anything and
+    /// execute anything that someone can execute
+    pub fn allows(&self, user: &Identity, uid: u32, gid: u32, access: Access) -> bool {
+        let bit = access as u32;
+        if user.is_root() {
+            return access != Access::Execute
+                || self.perms & TYPE_MASK == DIRECTORY
+                || self.perms & 0o111 != 0
+        }
+
+        // Only the most specific class that applies is checked
+        let shift = if user.uid == uid {
+            6
+        } else if user.in_group(gid) {
+            3
+        } else {
+            0
+        };
+        (self.perms >> shift) & bit != 0
+    }
+
     /// Returns true if the permissions grabbed from 
     /// The u32 value are valid permissions
     pub fn is_valid(&self) -> bool {
-        self.perms < 512
+        self.perms <= 0o177777
     }
 }
 
 impl From<u32> for Permissions {
     fn from(num: u32) -> Self {
-        Self { perms: num & 511 }
+        Self { perms: num }
      }
 }
 
@@ -41,49 +199,37 @@ impl std::fmt::Display for Permissions {
             return Ok(())
         }
 
-        let mut arr: [u8; 9] = [0; 9];
-        arr
-            .iter_mut()
-            .enumerate()
-            .map(|(i, _)| (i, (self.perms >> i) & 0b1))
-            .rev()
-            .for_each(|(i, b)| {
-                let rwx = i % 3;
-                if rwx == 0 {
-                    match b {
-                        0 => write!(f, "-").unwrap(),
-                        1 => write!(f, "x").unwrap(),
-                        _ => write!(f, "?").unwrap(),
-                    }
-                } else if rwx == 1 {
-                    match b {
-                        0 => write!(f, "-").unwrap(),
-                        1 => write!(f, "w").unwrap(),
-                        _ => write!(f, "?").unwrap(),
-                    } 
-                } else {
-                    match b {
-                        0 => write!(f, "-").unwrap(),
-                        1 => write!(f, "r").unwrap(),
-                        _ => write!(f, "?").unwrap(),
-                    } 
-                }
+        // Each class is read, write then execute, where execute also
+        // shows the setuid, setgid or sticky bit of that class
+        let special = [(SETUID, 's'), (SETGID, 's'), (STICKY, 't')];
+        let mut s = String::from(self.type_char());
+        for (class, (bit, ch)) in special.into_iter().enumerate() {
+            let rwx = self.perms >> (6 - class * 3);
+            s.push(if rwx & 4 != 0 { 'r' } else { '-' });
+            s.push(if rwx & 2 != 0 { 'w' } else { '-' });
+            s.push(match (rwx & 1 != 0, self.perms & bit != 0) {
+                (true, true) => ch,
+                (false, true) => ch.to_ascii_uppercase(),
+                (true, false) => 'x',
+                (false, false) => '-',
             });
+        }
 
-        Ok(())
+        write!(f, "{}", s)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Permissions;
+    use super::{Access, Permissions};
+    use crate::users::Identity;
 
     fn testing_perm_all() -> Permissions {
-        Permissions::from(511)
+        Permissions::from(0o100777)
     }
 
     fn testing_perm_none() -> Permissions {
-        Permissions::from(0)
+        Permissions::from(0o100000)
     }
 
     #[test]
@@ -97,6 +243,8 @@ mod tests {
         let perm = testing_perm_none();
         // passes if user does not have exec perms
         assert!(!perm.is_user_exec());
+        // only the other class can execute
+        assert!(!Permissions::from(0o100001).is_user_exec());
     }
 
      #[test]
@@ -110,6 +258,7 @@ mod tests {
         let perm = testing_perm_none();
         // passes if user does not have exec perms
         assert!(!perm.is_user_write());
+        assert!(!Permissions::from(0o100002).is_user_write());
     }
 
     #[test]
@@ -123,17 +272,76 @@ mod tests {
         let perm = testing_perm_none();
         // passes if user does not have exec perms
         assert!(!perm.is_user_read());
+        assert!(!Permissions::from(0o100004).is_user_read());
     } 
 
     #[test]
     fn test_display1() {
         let perm = testing_perm_all();
-        assert_eq!(String::from("rwxrwxrwx"), format!("{}", perm))
+        assert_eq!(String::from("-rwxrwxrwx"), format!("{}", perm))
     }
 
     #[test]
     fn test_display2() {
         let perm = testing_perm_none();
-        assert_eq!(String::from("---------"), format!("{}", perm))
+        assert_eq!(String::from("----------"), format!("{}", perm))
+    }
+
+    #[test]
+    fn test_display_special() {
+        assert_eq!(Permissions::from(0o044755).to_string(), "drwsr-xr-x");
+        assert_eq!(Permissions::from(0o041757).to_string(), "drwxr-xrwt");
+        assert_eq!(Permissions::from(0o102644).to_string(), "-rw-r-Sr--");
+        assert_eq!(Permissions::from(0o120777).to_string(), "lrwxrwxrwx");
+        assert_eq!(Permissions::from(u32::MAX).to_string(), "Error Reading Permissions!");
+    }
+
+    #[test]
+    fn test_octal() {
+        assert_eq!(Permissions::from(0o100644).octal(), "0644");
+        assert_eq!(Permissions::from(0o044755).octal(), "4755");
+        assert_eq!(Permissions::from(0o041777).mode(), 0o1777);
+    }
+
+    #[test]
+    fn test_apply() {
+        let file = Permissions::from(0o100644);
+        assert_eq!(file.apply("755"), Some(Permissions::from(0o100755)));
+        assert_eq!(file.apply("u+x,go-r"), Some(Permissions::from(0o100700)));
+        assert_eq!(file.apply("a=rw"), Some(Permissions::from(0o100666)));
+        assert_eq!(file.apply("+x-w"), Some(Permissions::from(0o100555)));
+        assert_eq!(file.apply("u+s,+t"), Some(Permissions::from(0o105644)));
+        assert_eq!(file.apply("g=u"), None);
+        assert_eq!(file.apply("u"), None);
+        assert_eq!(file.apply("u+x,"), None);
+        assert_eq!(file.apply(""), Some(file));
+        // Anything that isn't ASCII is invalid rather than cutting a character in half
+        assert_eq!(file.apply("ué"), None);
+        assert_eq!(file.apply("u+é"), None);
+        assert_eq!(file.apply("é"), None);
+        assert_eq!(file.apply("77777"), None);
+
+        // X only adds execute to directories and executables
+        assert_eq!(file.apply("a+X"), Some(file));
+        assert_eq!(Permissions::from(0o040600).apply("a+X"), Some(Permissions::from(0o040711)));
+        assert_eq!(Permissions::from(0o100744).apply("go+X"), Some(Permissions::from(0o100755)));
+    }
+
+    #[test]
+    fn test_allows() {
+        let user = Identity { uid: 1000, gid: 1000, groups: vec![1000, 27] };
+        let perm = Permissions::from(0o100640);
+        assert!(perm.allows(&user, 1000, 0, Access::Write));
+        assert!(perm.allows(&user, 0, 27, Access::Read));
+        assert!(!perm.allows(&user, 0, 27, Access::Write));
+        assert!(!perm.allows(&user, 0, 0, Access::Read));
+
+        // The owner doesn't fall back to what the group or others can do
+        assert!(!Permissions::from(0o100077).allows(&user, 1000, 1000, Access::Read));
+
+        let root = Identity { uid: 0, gid: 0, groups: vec![] };
+        assert!(perm.allows(&root, 1000, 1000, Access::Write));
+        assert!(!perm.allows(&root, 1000, 1000, Access::Execute));
+        assert!(Permissions::from(0o040700).allows(&root, 1000, 1000, Access::Execute));
     }
 }
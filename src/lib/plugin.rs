@@ -0,0 +1,500 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::action::Action;
+use super::app::App;
+use super::config::{is_valid_key, Command};
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, FuncArgs, FuncRegistration, Module, AST};
+
+/// A function registered by a script along with the script it came from
+#[derive(Clone)]
+struct Callback {
+    script: usize,
+    func: FnPtr,
+}
+
+/// State shared between fm and the functions of the `fm` module
+#[derive(Default)]
+struct Shared {
+    // Snapshot of the App taken before a script is called
+    cwd: String,
+    current: String,
+    selection: Vec<String>,
+    files: Vec<String>,
+    // Index of the script currently being loaded
+    script: usize,
+    // Registered by the scripts
+    commands: Vec<(Command, Callback)>,
+    binds: Vec<(String, String)>,
+    hooks: Vec<(String, Callback)>,
+    previewers: Vec<(String, Callback)>,
+    // Requests made by the scripts while they were running
+    actions: Vec<Action>,
+    message: Option<String>,
+}
+
+/// Rhai scripts loaded from the plugins directory next to the config file
+pub struct Plugins {
+    engine: Engine,
+    scripts: Vec<AST>,
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl Plugins {
+    /// Creates an engine with the `fm` module registered and no scripts
+    pub fn new() -> Self {
+        let shared = Rc::new(RefCell::new(Shared::default()));
+        let mut engine = Engine::new();
+        engine.register_static_module("fm", fm_module(&shared).into());
+
+        Self { engine, scripts: vec![], shared }
+    }
+
+    /// Loads every `.rhai` file in dir in alphabetical order,
+    /// errors are reported through the App
+    pub fn load(dir: &Path, app: &mut App) -> Self {
+        let mut plugins = Self::new();
+
+        let mut paths = match std::fs::read_dir(dir) {
+            Ok(iter) => iter
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "rhai"))
+                .collect::<Vec<_>>(),
+            Err(_) => return plugins,
+        };
+        paths.sort();
+
+        for path in paths {
+            let name = path.file_name().unwrap().to_string_lossy().to_string();
+            match std::fs::read_to_string(&path) {
+                Ok(source) => plugins.add_script(&name, &source, app),
+                Err(e) => app.err(&format!("{}: {}", name, e)),
+            }
+        }
+
+        plugins
+    }
+
+    /// Runs the top level of a script so it can register its commands,
+    /// hooks and previewers then adds its commands to the App's config
+    pub fn add_script(&mut self, name: &str, source: &str, app: &mut App) {
+        let ast = match self.engine.compile(source) {
+            Ok(ast) => ast,
+            Err(e) => return app.err(&format!("{}: {}", name, e)),
+        };
+
+        self.sync(app);
+        self.shared.borrow_mut().script = self.scripts.len();
+        let result = self.engine.run_ast(&ast);
+        self.scripts.push(ast);
+
+        if let Err(e) = result {
+            app.err(&format!("{}: {}", name, e))
+        }
+
+        self.register_commands(app);
+        self.apply(app);
+    }
+
+    /// Runs the plugin command with the given name
+    pub fn run_command(&self, name: &str, app: &mut App) {
+        let callback = self.shared.borrow()
+            .commands
+            .iter()
+            .find(|(c, _)| c.name == name)
+            .map(|(_, cb)| cb.clone());
+
+        match callback {
+            Some(cb) => { self.call(&cb, (), app); },
+            None => app.err(&format!("Unknown plugin command: {}", name)),
+        }
+    }
+
+    /// Calls every hook registered for event with arg,
+    /// events are `cd` and `open`
+    pub fn emit(&self, event: &str, arg: &str, app: &mut App) {
+        let hooks = self.shared.borrow()
+            .hooks
+            .iter()
+            .filter(|(e, _)| e == event)
+            .map(|(_, cb)| cb.clone())
+            .collect::<Vec<_>>();
+
+        for cb in hooks {
+            self.call(&cb, (arg.to_string(),), app);
+        }
+    }
+
+    /// Caches the preview of the selected file from the first previewer
+    /// registered for its extension in app.preview. Files without one are
+    /// cached too so failing previewers only run and report errors once
+    pub fn update_preview(&self, app: &mut App) {
+        let path = match app.selected_file() {
+            Some(file) => file.path().to_owned(),
+            None => return app.preview = None,
+        };
+
+        if matches!(&app.preview, Some((p, _)) if p == &path) {
+            return
+        }
+
+        let ext = path.extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let previewers = self.shared.borrow()
+            .previewers
+            .iter()
+            .filter(|(pattern, _)| pattern == "*" || pattern.to_lowercase() == ext)
+            .map(|(_, cb)| cb.clone())
+            .collect::<Vec<_>>();
+
+        let preview = previewers
+            .iter()
+            .filter_map(|cb| self.call(cb, (path.display().to_string(),), app))
+            .filter_map(|d| d.into_string().ok())
+            .find(|s| !s.is_empty());
+
+        app.preview = Some((path, preview));
+    }
+
+    /// Calls a script function with a fresh snapshot of the App then
+    /// applies whatever the script asked for
+    fn call(&self, cb: &Callback, args: impl FuncArgs, app: &mut App) -> Option<Dynamic> {
+        self.sync(app);
+        let result = cb.func.call::<Dynamic>(&self.engine, &self.scripts[cb.script], args);
+        self.apply(app);
+        self.register_commands(app);
+
+        match result {
+            Ok(d) => Some(d),
+            Err(e) => {
+                app.err(&format!("{}: {}", cb.func.fn_name(), e));
+                None
+            }
+        }
+    }
+
+    /// Copies the parts of the App the scripts can read
+    fn sync(&self, app: &App) {
+        let mut shared = self.shared.borrow_mut();
+        shared.cwd = app.wd.cwd().display().to_string();
+        shared.current = app.selected_file()
+            .map(|f| f.path().display().to_string())
+            .unwrap_or_default();
+        shared.selection = app.selection
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect();
+        shared.files = app.displayed_files
+            .iter()
+            .map(|f| f.path().display().to_string())
+            .collect();
+    }
+
+    /// Queues the actions and shows the message left by a script
+    fn apply(&self, app: &mut App) {
+        let mut shared = self.shared.borrow_mut();
+        shared.actions.drain(..).for_each(|a| app.queue(a));
+        if let Some(msg) = shared.message.take() {
+            app.err(&msg)
+        }
+    }
+
+    /// Adds the commands and binds registered since the
+    /// last call to the App's config
+    fn register_commands(&self, app: &mut App) {
+        let mut shared = self.shared.borrow_mut();
+
+        for (key, name) in std::mem::take(&mut shared.binds) {
+            if !is_valid_key(&key) {
+                app.err(&format!("Invalid key: {}", key));
+                continue
+            }
+
+            // Binding a plugin command
+            if let Some((cmd, _)) = shared.commands.iter_mut().find(|(c, _)| c.name == name) {
+                cmd.key = Some(key.clone());
+                if let Some(cmd) = app.config.commands.iter_mut().find(|c| c.name == name) {
+                    cmd.key = Some(key);
+                }
+                continue
+            }
+
+            // Binding a builtin action
+
+            match name.parse::<Action>() {
+                Ok(action) => app.config.commands.push(Command {
+                    description: action.description().to_string(),
+                    name,
+                    key: Some(key),
+                    shell: None,
+                    actions: Some(vec![action]),
+                }),
+                Err(e) => app.err(&e),
+            }
+        }
+
+        for (cmd, _) in &shared.commands {
+            if app.config.command(&cmd.name).is_none() {
+                app.config.commands.push(cmd.clone())
+            }
+        }
+    }
+}
+
+impl Default for Plugins {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The plugins directory next to a config file
+pub fn dir(config_path: &Path) -> PathBuf {
+    config_path.parent().unwrap_or(Path::new(".")).join("plugins")
+}
+
+type FnResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Builds the `fm` module exposed to scripts
+fn fm_module(shared: &Rc<RefCell<Shared>>) -> Module {
+    let mut module = Module::new();
+
+    // Reading the App's state
+    let s = shared.clone();
+    FuncRegistration::new("cwd")
+        .with_volatility(true)
+        .set_into_module(&mut module, move || s.borrow().cwd.clone());
+
+    let s = shared.clone();
+    FuncRegistration::new("current")
+        .with_volatility(true)
+        .set_into_module(&mut module, move || s.borrow().current.clone());
+
+    let s = shared.clone();
+    FuncRegistration::new("selection")
+        .with_volatility(true)
+        .set_into_module(&mut module, move || to_array(&s.borrow().selection));
+
+    let s = shared.clone();
+    FuncRegistration::new("files")
+        .with_volatility(true)
+        .set_into_module(&mut module, move || to_array(&s.borrow().files));
+
+    // Registering commands, keybinds, hooks and previewers
+    let s = shared.clone();
+    FuncRegistration::new("command")
+        .with_volatility(true)
+        .set_into_module(&mut module, move |name: &str, description: &str, func: FnPtr| {
+            let mut shared = s.borrow_mut();
+            let script = shared.script;
+            let cmd = Command {
+                name: name.to_string(),
+                description: description.to_string(),
+                key: None,
+                shell: None,
+                actions: Some(vec![Action::Plugin(name.to_string())]),
+            };
+            shared.commands.retain(|(c, _)| c.name != name);
+            shared.commands.push((cmd, Callback { script, func }));
+        });
+
+    let s = shared.clone();
+    FuncRegistration::new("bind")
+        .with_volatility(true)
+        .set_into_module(&mut module, move |key: &str, name: &str| {
+            s.borrow_mut().binds.push((key.to_string(), name.to_string()));
+        });
+
+    let s = shared.clone();
+    FuncRegistration::new("on")
+        .with_volatility(true)
+        .set_into_module(&mut module, move |event: &str, func: FnPtr| -> FnResult<()> {
+            if !matches!(event, "cd" | "open") {
+                return Err(format!("Unknown event: {}", event).into())
+            }
+
+            let mut shared = s.borrow_mut();
+            let script = shared.script;
+            shared.hooks.push((event.to_string(), Callback { script, func }));
+            Ok(())
+        });
+
+    let s = shared.clone();
+    FuncRegistration::new("previewer")
+        .with_volatility(true)
+        .set_into_module(&mut module, move |ext: &str, func: FnPtr| {
+            let mut shared = s.borrow_mut();
+            let script = shared.script;
+            shared.previewers.push((ext.to_string(), Callback { script, func }));
+        });
+
+    // Asking the App to do something
+    let s = shared.clone();
+    FuncRegistration::new("run")
+        .with_volatility(true)
+        .set_into_module(&mut module, move |name: &str| -> FnResult<()> {
+            let mut shared = s.borrow_mut();
+            let action = match shared.commands.iter().find(|(c, _)| c.name == name) {
+                Some(_) => Action::Plugin(name.to_string()),
+                None => name.parse::<Action>()?,
+            };
+            shared.actions.push(action);
+            Ok(())
+        });
+
+    let s = shared.clone();
+    FuncRegistration::new("message")
+        .with_volatility(true)
+        .set_into_module(&mut module, move |msg: &str| {
+            s.borrow_mut().message = Some(msg.to_string());
+        });
+
+    module
+}
+
+fn to_array(v: &[String]) -> Array {
+    v.iter().cloned().map(Dynamic::from).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Plugins;
+    use crate::action::Action;
+    use crate::app::{App, InputMode};
+    use crate::config::Config;
+    use std::path::{Path, PathBuf};
+
+    const SCRIPT: &str = r#"
+        fn hello() {
+            fm::run("bottom");
+            fm::run("!echo " + fm::cwd());
+        }
+
+        fn preview(path) {
+            "preview of " + path
+        }
+
+        fn later() {
+            fm::bind("L", "bottom");
+        }
+
+        fn broken(path) {
+            throw "no preview";
+        }
+
+        fm::command("hello", "Say hello", Fn("hello"));
+        fm::command("later", "Bind a key", Fn("later"));
+        fm::bind("H", "hello");
+        fm::bind("ctrl-t", "top");
+        fm::previewer("toml", Fn("preview"));
+        fm::previewer("md", Fn("broken"));
+    "#;
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fm-plugin-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "").unwrap();
+        std::fs::write(dir.join("README.md"), "").unwrap();
+        dir
+    }
+
+    /// An App in its own directory with the default config rather than the user's
+    fn testing_app(dir: &Path) -> App {
+        App::with_config(Some(dir), Config::default()).unwrap()
+    }
+
+    #[test]
+    fn test_register_command() {
+        let dir = testing_dir("register-command");
+        let mut app = testing_app(&dir);
+        let mut plugins = Plugins::new();
+        plugins.add_script("test.rhai", SCRIPT, &mut app);
+
+        let cmd = app.config.command("hello").unwrap();
+        assert_eq!(cmd.key.as_deref(), Some("H"));
+        assert_eq!(cmd.actions(), vec![Action::Plugin("hello".to_string())]);
+        assert_eq!(app.config.command("top").unwrap().key.as_deref(), Some("ctrl-t"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_command() {
+        let dir = testing_dir("run-command");
+        let mut app = testing_app(&dir);
+        let mut plugins = Plugins::new();
+        plugins.add_script("test.rhai", SCRIPT, &mut app);
+        plugins.run_command("hello", &mut app);
+
+        let cwd = app.wd.cwd().display().to_string();
+        assert_eq!(app.actions.pop_front(), Some((Action::Bottom, None)));
+        assert_eq!(app.actions.pop_front(), Some((Action::Shell(format!("echo {}", cwd)), None)));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_previewer() {
+        let dir = testing_dir("previewer");
+        let mut app = testing_app(&dir);
+        let mut plugins = Plugins::new();
+        plugins.add_script("test.rhai", SCRIPT, &mut app);
+
+        let path = app.wd.cwd().join("Cargo.toml");
+        app.select_file(&path);
+        plugins.update_preview(&mut app);
+        assert_eq!(app.preview, Some((path.clone(), Some(format!("preview of {}", path.display())))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_failing_previewer() {
+        let dir = testing_dir("failing");
+        let mut app = testing_app(&dir);
+        let mut plugins = Plugins::new();
+        plugins.add_script("test.rhai", SCRIPT, &mut app);
+
+        let path = app.wd.cwd().join("README.md");
+        app.select_file(&path);
+        plugins.update_preview(&mut app);
+        assert!(app.err_msg.contains("no preview"));
+        assert_eq!(app.preview, Some((path, None)));
+
+        // The failure is cached so fm can leave the error
+        app.to_normal_mode();
+        plugins.update_preview(&mut app);
+        assert_eq!(app.input_mode, InputMode::Normal);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bind_after_load() {
+        let dir = testing_dir("bind");
+        let mut app = testing_app(&dir);
+        let mut plugins = Plugins::new();
+        plugins.add_script("test.rhai", SCRIPT, &mut app);
+        assert!(app.config.command("bottom").is_none());
+
+        plugins.run_command("later", &mut app);
+        assert_eq!(app.config.command("bottom").unwrap().key.as_deref(), Some("L"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_script_error() {
+        let dir = testing_dir("script-error");
+        let mut app = testing_app(&dir);
+        let mut plugins = Plugins::new();
+        plugins.add_script("bad.rhai", "fm::on(\"nope\", Fn(\"x\"));", &mut app);
+        assert!(app.err_msg.contains("Unknown event"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
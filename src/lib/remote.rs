@@ -0,0 +1,286 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::app::App;
+
+use anyhow::{anyhow, bail, Context};
+
+// How long a client gets to send its command or read the reply
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A command received from a client and the channel to send the reply on
+pub struct Request {
+    pub command: String,
+    pub reply: mpsc::Sender<Result<String, String>>,
+}
+
+/// Commands understood by a running fm
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Change to a directory
+    Cd(PathBuf),
+    /// Move the cursor to a file, changing directory if needed
+    Select(PathBuf),
+    /// Reread the current directory
+    Reload,
+    /// Ask for `cwd`, `current`, `selection` or `files`
+    Query(String),
+    /// Run a command or action like the command line does
+    Send(String),
+}
+
+impl std::str::FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (cmd, arg) = match s.split_once(' ') {
+            Some((cmd, arg)) => (cmd, arg.trim()),
+            None => (s, ""),
+        };
+
+        let needs_arg = |what: &str| match arg.is_empty() {
+            true => Err(format!("{} needs {}", cmd, what)),
+            false => Ok(arg.to_string()),
+        };
+
+        match cmd {
+            "cd" => Ok(Command::Cd(PathBuf::from(needs_arg("a directory")?))),
+            "select" => Ok(Command::Select(PathBuf::from(needs_arg("a file")?))),
+            "reload" => Ok(Command::Reload),
+            "query" => match arg {
+                "cwd" | "current" | "selection" | "files" => Ok(Command::Query(arg.to_string())),
+                _ => Err("query needs cwd, current, selection or files".to_string()),
+            },
+            "send" => Ok(Command::Send(needs_arg("a command")?)),
+            _ => Err(format!("Unknown command: {}", cmd)),
+        }
+    }
+}
+
+impl Command {
+    /// Runs the command against the App, returning the reply for the client
+    pub fn run(self, app: &mut App) -> Result<String, String> {
+        // Relative paths are relative to fm's directory
+        let absolute = |path: PathBuf| app.wd.cwd().join(path);
+
+        match self {
+            Command::Cd(dir) => {
+                let dir = absolute(dir);
+                app.cd(&dir).map_err(|e| format!("{:#}", e))?
+            },
+            Command::Select(file) => {
+                let file = absolute(file);
+                if !file.exists() {
+                    return Err(format!("{} doesn't exist", file.display()))
+                }
+                app.select_file(&file)
+            },
+            Command::Reload => app.reload(),
+            Command::Query(what) => {
+                let paths = match what.as_str() {
+                    "cwd" => vec![app.wd.cwd().clone()],
+                    "current" => app.selected_file().map(|f| f.path.clone()).into_iter().collect(),
+                    "selection" => app.selection.iter().cloned().collect(),
+                    _ => app.displayed_files.iter().map(|f| f.path.clone()).collect(),
+                };
+
+                return Ok(paths.iter().map(|p| format!("{}\n", p.display())).collect())
+            },
+            Command::Send(cmd) => app.run_command(&cmd).map_err(|e| e.to_string())?,
+        }
+
+        Ok(String::new())
+    }
+}
+
+/// Directory holding the sockets of every running fm
+pub fn socket_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("fm"),
+        // SAFETY: getuid can't fail
+        _ => std::env::temp_dir().join(format!("fm-{}", unsafe { libc::getuid() })),
+    }
+}
+
+/// Path of the socket for the fm with the given pid
+pub fn socket_path(pid: u32) -> PathBuf {
+    socket_dir().join(format!("{}.sock", pid))
+}
+
+/// Listens on the socket for this process, every request is handed to
+/// forward which returns false once nobody is listening anymore.
+/// Returns the path of the socket so it can be removed on exit
+pub fn listen<F>(forward: F) -> anyhow::Result<PathBuf>
+where
+    F: Fn(Request) -> bool + Send + 'static,
+{
+    let dir = socket_dir();
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("Can't create {}", dir.display()))?;
+
+    // A socket left behind by a crashed fm that had our pid
+    let path = socket_path(std::process::id());
+    if path.exists() {
+        std::fs::remove_file(&path)?;
+    }
+
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Can't listen on {}", path.display()))?;
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if !handle_client(stream, &forward) {
+                break
+            }
+        }
+    });
+
+    Ok(path)
+}
+
+/// Reads one command from a client and writes back the reply, clients
+/// that connect without sending anything are only checking we're alive
+fn handle_client<F: Fn(Request) -> bool>(mut stream: UnixStream, forward: &F) -> bool {
+    // A stuck client mustn't keep everyone else waiting
+    if stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_err()
+        || stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_err()
+    {
+        return true
+    }
+
+    let mut command = String::new();
+    if BufReader::new(&stream).read_line(&mut command).is_err() || command.trim().is_empty() {
+        return true
+    }
+
+    let (reply, rx) = mpsc::channel();
+    if !forward(Request { command, reply }) {
+        return false
+    }
+
+    let reply = match rx.recv_timeout(Duration::from_secs(5)) {
+        Ok(Ok(reply)) => reply,
+        Ok(Err(e)) => format!("error: {}\n", e),
+        Err(_) => "error: fm didn't reply\n".to_string(),
+    };
+
+    let _ = stream.write_all(reply.as_bytes());
+    true
+}
+
+/// Sends a command to the fm with the given pid, or the only one running
+/// if there's no pid, and returns its reply
+pub fn send(pid: Option<u32>, command: &str) -> anyhow::Result<String> {
+    let path = match pid {
+        Some(pid) => socket_path(pid),
+        None => find_socket()?,
+    };
+
+    let mut stream = UnixStream::connect(&path)
+        .with_context(|| format!("Can't connect to {}", path.display()))?;
+    stream.write_all(command.as_bytes())?;
+    stream.write_all(b"\n")?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+
+    match reply.strip_prefix("error: ") {
+        Some(e) => Err(anyhow!("{}", e.trim_end())),
+        None => Ok(reply),
+    }
+}
+
+/// Finds the socket of the only fm that is running
+fn find_socket() -> anyhow::Result<PathBuf> {
+    let dir = socket_dir();
+    let sockets = match std::fs::read_dir(&dir) {
+        Ok(iter) => iter
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "sock"))
+            .filter(|p| UnixStream::connect(p).is_ok())
+            .collect::<Vec<_>>(),
+        Err(_) => vec![],
+    };
+
+    match sockets.len() {
+        0 => bail!("No running fm found in {}", dir.display()),
+        1 => Ok(sockets.into_iter().next().unwrap()),
+        _ => bail!("Several fm are running, give the pid of one"),
+    }
+}
+
+/// Builds the command line to send from the arguments given to
+/// `fm -remote`, paths given to cd and select are made absolute
+pub fn client_command(args: &[String], cwd: &Path) -> (Option<u32>, String) {
+    let (pid, args) = match args.first().and_then(|a| a.parse::<u32>().ok()) {
+        Some(pid) => (Some(pid), &args[1..]),
+        None => (std::env::var("FM_PID").ok().and_then(|p| p.parse().ok()), args),
+    };
+
+    let command = match args {
+        [cmd, path] if cmd == "cd" || cmd == "select" => {
+            format!("{} {}", cmd, cwd.join(path).display())
+        },
+        _ => args.join(" "),
+    };
+
+    (pid, command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{client_command, handle_client, Command, CLIENT_TIMEOUT};
+    use std::os::unix::net::UnixStream;
+    use std::path::{Path, PathBuf};
+    use std::time::Instant;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("cd /tmp".parse(), Ok(Command::Cd(PathBuf::from("/tmp"))));
+        assert_eq!("select my file".parse(), Ok(Command::Select(PathBuf::from("my file"))));
+        assert_eq!("reload\n".parse(), Ok(Command::Reload));
+        assert_eq!("query selection".parse(), Ok(Command::Query("selection".to_string())));
+        assert_eq!("send !echo hi".parse(), Ok(Command::Send("!echo hi".to_string())));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("cd".parse::<Command>().is_err());
+        assert!("query nothing".parse::<Command>().is_err());
+        assert!("dance".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn test_probe_is_ignored() {
+        let (client, server) = UnixStream::pair().unwrap();
+        drop(client);
+        assert!(handle_client(server, &|_| panic!("an empty request was forwarded")));
+    }
+
+    #[test]
+    fn test_silent_client_times_out() {
+        let (_client, server) = UnixStream::pair().unwrap();
+        let start = Instant::now();
+        assert!(handle_client(server, &|_| panic!("nothing was sent")));
+        assert!(start.elapsed() < CLIENT_TIMEOUT * 3);
+    }
+
+    #[test]
+    fn test_client_command() {
+        let args = ["123", "select", "file"].map(String::from);
+        assert_eq!(client_command(&args, Path::new("/home")), (Some(123), "select /home/file".to_string()));
+
+        let args = ["send", "!echo", "hi"].map(String::from);
+        assert_eq!(client_command(&args, Path::new("/home")).1, "send !echo hi");
+    }
+}
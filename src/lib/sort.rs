@@ -0,0 +1,204 @@
+use std::cmp::Ordering;
+use std::path::Path;
+
+use super::file::File;
+use super::filetype::FileType;
+
+use serde::Deserialize;
+
+/// What files are listed by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortBy {
+    /// Names with the numbers in them compared by value
+    Natural,
+    /// Biggest first
+    Size,
+    /// Newest first
+    Modified,
+    /// Newest first, falling back to when the file last changed
+    Created,
+    Extension,
+    /// Directories, symlinks, executables then files
+    Type,
+}
+
+impl SortBy {
+    pub fn all() -> [SortBy; 6] {
+        use SortBy::*;
+        [Natural, Size, Modified, Created, Extension, Type]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortBy::Natural => "natural",
+            SortBy::Size => "size",
+            SortBy::Modified => "modified",
+            SortBy::Created => "created",
+            SortBy::Extension => "extension",
+            SortBy::Type => "type",
+        }
+    }
+
+    fn cmp(&self, a: &File, b: &File) -> Ordering {
+        let order = match self {
+            SortBy::Natural => Ordering::Equal,
+            SortBy::Size => b.size.cmp(&a.size),
+            SortBy::Modified => b.modified.cmp(&a.modified),
+            SortBy::Created => b.created.cmp(&a.created),
+            SortBy::Extension => extension(&a.name).cmp(extension(&b.name)),
+            SortBy::Type => type_rank(a.ftype).cmp(&type_rank(b.ftype)),
+        };
+
+        // Anything else that's the same is sorted by name
+        order.then_with(|| natural_cmp(sort_name(&a.name), sort_name(&b.name)))
+    }
+}
+
+/// How files are listed, set in the config and changed at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sort {
+    pub by: SortBy,
+    pub reverse: bool,
+    pub dirs_first: bool,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Self { by: SortBy::Natural, reverse: false, dirs_first: false }
+    }
+}
+
+impl Sort {
+    /// Sorts the files, reversing doesn't move directories to the end
+    pub fn sort(&self, files: &mut [File]) {
+        files.sort_by(|a, b| {
+            let dirs = match self.dirs_first {
+                true => (b.ftype == FileType::Directory).cmp(&(a.ftype == FileType::Directory)),
+                false => Ordering::Equal,
+            };
+
+            dirs.then_with(|| match self.reverse {
+                true => self.by.cmp(b, a),
+                false => self.by.cmp(a, b),
+            })
+        })
+    }
+}
+
+/// Compares names like a person would, the numbers in them are
+/// compared by value so `file2` comes before `file10`
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+
+    loop {
+        let (x, y) = match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => (x, y),
+        };
+
+        if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (na, rest_a) = split_number(a);
+            let (nb, rest_b) = split_number(b);
+            let order = number_cmp(na, nb);
+            if order != Ordering::Equal {
+                return order
+            }
+            a = rest_a;
+            b = rest_b;
+        } else if x != y {
+            return x.cmp(&y)
+        } else {
+            a = &a[x.len_utf8()..];
+            b = &b[y.len_utf8()..];
+        }
+    }
+}
+
+/// Splits the leading digits off s
+fn split_number(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Compares numbers of any length, with fewer leading zeros first when equal
+fn number_cmp(a: &str, b: &str) -> Ordering {
+    let (ta, tb) = (a.trim_start_matches('0'), b.trim_start_matches('0'));
+    ta.len().cmp(&tb.len())
+        .then_with(|| ta.cmp(tb))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+/// Hidden files are sorted as if they didn't start with a dot
+fn sort_name(name: &str) -> &str {
+    name.strip_prefix('.').unwrap_or(name)
+}
+
+/// Files without an extension come first
+fn extension(name: &str) -> &str {
+    Path::new(sort_name(name))
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+}
+
+fn type_rank(ftype: FileType) -> u8 {
+    match ftype {
+        FileType::Directory => 0,
+        FileType::Symlink => 1,
+        FileType::Executable => 2,
+        FileType::File => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{natural_cmp, Sort, SortBy};
+    use crate::file::File;
+    use std::cmp::Ordering;
+    use std::path::PathBuf;
+
+    fn names(files: &[File]) -> Vec<&str> {
+        files.iter().map(|f| f.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.10.0", "v1.9.3"), Ordering::Greater);
+        assert_eq!(natural_cmp("a01", "a1"), Ordering::Greater);
+        assert_eq!(natural_cmp("a", "ab"), Ordering::Less);
+        assert_eq!(natural_cmp("abc", "abc"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut files = ["b.txt", "a10.rs", ".a2.rs", "c"]
+            .iter()
+            .map(|name| File::from(PathBuf::from(name)))
+            .collect::<Vec<_>>();
+
+        Sort::default().sort(&mut files);
+        assert_eq!(names(&files), vec![".a2.rs", "a10.rs", "b.txt", "c"]);
+
+        let sort = Sort { by: SortBy::Extension, reverse: true, dirs_first: false };
+        sort.sort(&mut files);
+        assert_eq!(names(&files), vec!["b.txt", "a10.rs", ".a2.rs", "c"]);
+    }
+
+    #[test]
+    fn test_dirs_first() {
+        let dir = std::env::temp_dir().join(format!("fm-sort-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("b")).unwrap();
+        std::fs::write(dir.join("a"), "").unwrap();
+
+        let sort = Sort { by: SortBy::Natural, reverse: false, dirs_first: true };
+        let wd = crate::workingdir::WorkingDir::new(Some(&dir), crate::filter::Filter::default(), sort).unwrap();
+        assert_eq!(names(wd.files()), vec!["b", "a"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
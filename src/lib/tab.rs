@@ -0,0 +1,95 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use super::workingdir::WorkingDir;
+use super::file::File;
+
+use tui::widgets::ListState;
+
+/// A tab that isn't being shown. The shown tab lives in App, switching
+/// tabs swaps its state with one of these
+pub struct Tab {
+    pub wd: WorkingDir,
+    pub displayed_files: Vec<File>,
+    pub flist_state: ListState,
+    pub scroll: usize,
+    // The search narrowing down the listing
+    pub searching_for: String,
+    // Directories visited before the cwd, oldest first
+    pub history: Vec<PathBuf>,
+}
+
+impl Tab {
+    /// A tab in the same directory as wd with the cursor on the first file
+    pub fn new(wd: &WorkingDir) -> anyhow::Result<Self> {
+        let wd = wd.at(wd.cwd())?;
+        let displayed_files = wd.files().to_vec();
+
+        let mut flist_state = ListState::default();
+        if !displayed_files.is_empty() {
+            flist_state.select(Some(0));
+        }
+
+        Ok(Self {
+            wd,
+            displayed_files,
+            flist_state,
+            scroll: 0,
+            searching_for: String::new(),
+            history: Vec::new(),
+        })
+    }
+
+    /// The name shown in the tab bar
+    pub fn name(&self) -> String {
+        tab_name(self.wd.cwd())
+    }
+
+    /// Rereads and sorts the directory, going up to a directory that
+    /// exists if it was removed and keeping the cursor in bounds
+    pub fn reload(&mut self) {
+        if !self.wd.cwd().is_dir() {
+            let parent = self.wd.cwd().ancestors().find(|p| p.is_dir()).map(Path::to_owned);
+            if let Some(parent) = parent {
+                let _ = self.wd.set_cwd(&parent);
+                self.searching_for.clear();
+            }
+        } else if self.wd.update().is_err() {
+            return
+        }
+
+        self.displayed_files = self.wd.files()
+            .iter()
+            .filter(|f| f.name.starts_with(&self.searching_for))
+            .cloned()
+            .collect();
+
+        let last = self.displayed_files.len().checked_sub(1);
+        let cursor = self.flist_state.selected().unwrap_or(0);
+        self.flist_state.select(last.map(|last| cursor.min(last)));
+    }
+
+    /// Moves the cursor to the file at path if it's listed
+    pub fn select(&mut self, path: &Path) {
+        if let Some(i) = self.displayed_files.iter().position(|f| f.path() == path) {
+            self.flist_state.select(Some(i))
+        }
+    }
+}
+
+/// The pane that isn't active in dual pane mode, it has its own
+/// selection on top of what a tab has
+pub struct Pane {
+    pub tab: Tab,
+    pub selection: BTreeSet<PathBuf>,
+    // Which side of the screen it's drawn on
+    pub left: bool,
+}
+
+/// The name of the directory, or `/` for the root
+pub fn tab_name(dir: &Path) -> String {
+    match dir.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => dir.display().to_string(),
+    }
+}
@@ -0,0 +1,83 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+// Times older than this show the year instead of the time of day, like ls
+const RECENT_SECS: i64 = 182 * 24 * 60 * 60;
+
+/// Formats a time in the local timezone like `ls -l`, with the time of
+/// day for the last six months and the year for anything else
+pub fn short(time: SystemTime) -> String {
+    let secs = seconds(time);
+    let tm = match local(secs) {
+        Some(tm) => tm,
+        None => return String::from("?"),
+    };
+
+    let month = MONTHS[tm.tm_mon.clamp(0, 11) as usize];
+    let age = seconds(SystemTime::now()) - secs;
+    match (0..RECENT_SECS).contains(&age) {
+        true => format!("{} {:>2} {:02}:{:02}", month, tm.tm_mday, tm.tm_hour, tm.tm_min),
+        false => format!("{} {:>2} {:>5}", month, tm.tm_mday, tm.tm_year + 1900),
+    }
+}
+
+/// Formats a time in the local timezone like `stat`, down to the
+/// nanosecond and with the offset from UTC
+pub fn full(time: SystemTime) -> String {
+    let secs = seconds(time);
+    let tm = match local(secs) {
+        Some(tm) => tm,
+        None => return String::from("?"),
+    };
+
+    let nanos = time.duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    let offset = tm.tm_gmtoff / 60;
+    let sign = if offset < 0 { '-' } else { '+' };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:09} {}{:02}{:02}",
+        tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday, tm.tm_hour, tm.tm_min, tm.tm_sec,
+        nanos, sign, offset.abs() / 60, offset.abs() % 60,
+    )
+}
+
+/// Seconds since the epoch, negative for times before it
+fn seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+/// Splits secs into a date and time in the local timezone
+fn local(secs: i64) -> Option<libc::tm> {
+    let secs = secs as libc::time_t;
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+    match unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        true => None,
+        false => Some(tm),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{full, short};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn test_short() {
+        // Old times show the year where recent ones show the time
+        let old = short(SystemTime::UNIX_EPOCH + Duration::from_secs(86400 * 40));
+        assert!(old.ends_with(" 1970"), "{}", old);
+        let now = short(SystemTime::now());
+        assert_eq!(now.len(), 12);
+        assert_eq!(&now[9..10], ":");
+    }
+
+    #[test]
+    fn test_full() {
+        let time = full(SystemTime::UNIX_EPOCH + Duration::new(86400 * 40, 5));
+        assert!(time.starts_with("1970-02-"), "{}", time);
+        assert_eq!(&time[19..30], ".000000005 ");
+        assert_eq!(time.len(), 35);
+    }
+}
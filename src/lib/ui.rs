@@ -1,97 +1,352 @@
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 use super::app::{App, InputMode};
-use super::workingdir::WorkingDir;
 use super::file::File;
 use super::filetype::FileType;
 use super::userinput::Input;
+use super::action::Action;
+use super::sort::Sort;
+use super::long;
+use super::time;
+use super::users::{Identity, Users};
+use super::permissions::Access;
 
 use tui::Frame;
 use tui::backend::Backend;
 use tui::layout::{Constraint, Layout, Alignment, Rect};
-use tui::text::{Text, Span};
+use tui::text::{Text, Span, Spans};
 use tui::style::{Style, Color, Modifier};
 use tui::widgets::{
     Block, BorderType, Borders, List, 
-    ListItem, Paragraph,
+    ListItem, ListState, Paragraph, Clear,
 };
 
+const SELECTED: Color = Color::Rgb(213, 0, 255);
+// Previews only read the start of big files
+const PREVIEW_BYTES: usize = 16 * 1024;
+// Smallest terminal the layout fits in
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 8;
+// Narrowest terminals that still fit the parent and preview columns
+const PARENT_MIN_WIDTH: u16 = 80;
+const PREVIEW_MIN_WIDTH: u16 = 50;
+
 pub fn draw<B: Backend>(f: &mut Frame<B>, app: &mut App, user_inp: &mut Box<dyn Input>) {
+    if too_small(f.size()) {
+        return render_too_small(f)
+    }
 
-    let selected_file = if app.selected_file().is_none() {
+    if app.selected_file().is_none() {
         render_empty(f, app, user_inp);
-        return 
     } else {
-        app.selected_file().unwrap().to_owned()
-    };
+        render_files(f, app, user_inp);
+    }
+
+    if app.input_mode == InputMode::Editing {
+        render_completions(f, app, user_inp);
+    }
+
+    if app.show_help {
+        render_help(f, app);
+    }
+
+    if app.show_info {
+        render_info(f, app);
+    }
+}
+
+fn render_files<B: Backend>(f: &mut Frame<B>, app: &mut App, user_inp: &mut Box<dyn Input>) {
+    let selected_file = app.selected_file().unwrap().to_owned();
 
-    let (chunks, middle_chunks) = gen_chunks(f);
-    let files = list_from_files(&app.displayed_files);
-    let list = gen_list(&files, &selected_file);
+    let areas = areas(app, f.size());
+    app.list_height = areas.list.height as usize;
+    app.update_scroll();
+
+    // Only the files that fit are rendered so fm controls the scrolling
+    let end = (app.scroll + app.list_height).min(app.displayed_files.len());
+    let mut list_state = ListState::default();
+    list_state.select(app.flist_state.selected().map(|i| i.saturating_sub(app.scroll)));
+
+    // The range in Visual mode is shown as selected
+    let marked = app.marked();
+    let width = areas.list.width.saturating_sub(2) as usize;
+    let files = list_from_listing(app, &marked, app.scroll..end, width);
+    let list = gen_list(&files, file_color(&selected_file, &marked));
+
+    if let Some(area) = areas.parent {
+        render_parent(f, app, &marked, area);
+    }
+    render_other_pane(f, app, &areas);
 
     match app.input_mode {
-        InputMode::Normal => {
-            let extra_chunks = nmode_extra_chunks(&chunks);
-            f.render_widget(gen_cwd(app.wd.cwd()), chunks[0]);
-            f.render_widget(gen_input(""), extra_chunks[4]);
+        InputMode::Normal | InputMode::Visual => {
+            let extra_chunks = nmode_extra_chunks(areas.footer);
+            f.render_widget(gen_cwd(app), areas.header);
+            f.render_widget(gen_status(app, marked.len()), extra_chunks[5]);
 
             // Render an empty screen for an empty directory
             if app.wd.files().is_empty() {
-                f.render_widget(invalid_prev("Empty Directory"), chunks[1])
+                f.render_widget(invalid_prev("Empty Directory"), areas.list)
             } else {
-                f.render_stateful_widget(list, middle_chunks[0], &mut app.flist_state);
+                f.render_stateful_widget(list, areas.list, &mut list_state);
             }
             
-            let (ex1, ex2, ex3) = gen_extras(
-                    &selected_file, app.flist_state.selected().unwrap(), 
+            let (ex1, ex2, ex3, ex4) = gen_extras(
+                    &selected_file, &app.users, app.flist_state.selected().unwrap(), 
                     app.displayed_files.len(), 
             );
 
             f.render_widget(ex1, extra_chunks[1]);
             f.render_widget(ex2, extra_chunks[2]);
             f.render_widget(ex3, extra_chunks[3]);
+            f.render_widget(ex4, extra_chunks[4]);
         }, 
 
         InputMode::Editing => {
-            f.render_widget(gen_cwd(app.wd.cwd()), chunks[0]);
-            f.render_widget(gen_input(&user_inp.output()), chunks[2]);
-            f.set_cursor(chunks[2].x + user_inp.output().len() as u16, chunks[2].y + 1);
-            f.render_widget(list, middle_chunks[0]);
+            f.render_widget(gen_cwd(app), areas.header);
+            f.render_widget(gen_input(&user_inp.output()), areas.footer);
+            f.set_cursor(areas.footer.x + user_inp.output().len() as u16, areas.footer.y + 1);
+            f.render_widget(list, areas.list);
         },
-        InputMode::Visual => {},
         InputMode::Error => {
-            f.render_widget(gen_cwd(app.wd.cwd()), chunks[0]);
-            f.render_widget(gen_err(&app.err_msg), chunks[2]);
-            f.render_widget(list, middle_chunks[0]);
+            f.render_widget(gen_cwd(app), areas.header);
+            f.render_widget(gen_err(&app.err_msg), areas.footer);
+            f.render_widget(list, areas.list);
         }
     };
 
+    let preview_area = match areas.preview {
+        Some(area) => area,
+        None => return,
+    };
+
+    let scroll = app.preview_scroll(selected_file.path());
+
+    // Previews supplied by plugins take priority
+    if let Some((path, Some(preview))) = &app.preview {
+        if path == selected_file.path() {
+            let preview = Paragraph::new(Text::from(preview.clone()))
+                .block(prev_block())
+                .scroll((u16::try_from(scroll).unwrap_or(u16::MAX), 0));
+            return f.render_widget(preview, preview_area)
+        }
+    }
+
     match selected_file.ftype {
         FileType::Directory => {
-            match gen_dir_preview(&selected_file) {
-                Ok(list) => f.render_widget(list, middle_chunks[1]),
-                Err(s) => f.render_widget(invalid_prev(s), middle_chunks[1])
+            match gen_dir_preview(&selected_file, app, scroll) {
+                Ok(list) => f.render_widget(list, preview_area),
+                Err(s) => f.render_widget(invalid_prev(s), preview_area)
             }
         },
         FileType::File => { 
             match gen_file_preview(&selected_file) {
-                Ok(file) => f.render_widget(file, middle_chunks[1]),
-                Err(s) => f.render_widget(invalid_prev(&s), middle_chunks[1]),
+                Ok(file) => f.render_widget(file.scroll((u16::try_from(scroll).unwrap_or(u16::MAX), 0)), preview_area),
+                Err(s) => f.render_widget(invalid_prev(&s), preview_area),
             }
         }
         _ => {}
     };
 }
 
+/// Returns true if the terminal is too small to draw fm in
+pub fn too_small(size: Rect) -> bool {
+    size.width < MIN_WIDTH || size.height < MIN_HEIGHT
+}
+
+/// Replaces everything with a message until the terminal is resized
+fn render_too_small<B: Backend>(f: &mut Frame<B>) {
+    let size = f.size();
+    let msg = Paragraph::new(vec![
+        Spans::from("Terminal too small"),
+        Spans::from(format!("{}x{}, needs {}x{}", size.width, size.height, MIN_WIDTH, MIN_HEIGHT)),
+    ])
+    .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+    .alignment(Alignment::Center)
+    .wrap(tui::widgets::Wrap { trim: true });
+
+    // Vertically centered when there's room
+    let y = size.height.saturating_sub(2) / 2;
+    f.render_widget(msg, Rect::new(size.x, size.y + y, size.width, size.height - y));
+}
+
+/// Lists the candidates of the current input above the input line
+fn render_completions<B: Backend>(f: &mut Frame<B>, app: &App, user_inp: &mut Box<dyn Input>) {
+    let completions = user_inp.completions(app);
+    if completions.is_empty() {
+        return
+    }
+
+    let areas = areas(app, f.size());
+    let height = (completions.len() as u16 + 1).min(areas.middle.height);
+    let area = Rect::new(areas.middle.x, areas.footer.y - height, areas.middle.width, height);
+
+    let width = completions.iter().map(|(c, _)| c.len()).max().unwrap_or(0) + 2;
+    let items = completions
+        .into_iter()
+        .map(|(c, desc)| {
+            ListItem::new(Spans::from(vec![
+                Span::styled(format!("{:<width$}", c, width = width), Style::default().fg(Color::LightBlue)),
+                Span::raw(desc),
+            ]))
+        })
+        .collect::<Vec<_>>();
+
+    f.render_widget(Clear, area);
+    f.render_widget(List::new(items).block(Block::default().borders(Borders::TOP)), area);
+}
+
+/// Draws a popup listing the builtin keybinds and the user's commands
+fn render_help<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let area = centered_rect(70, 80, f.size());
+
+    let key_style = Style::default().fg(Color::LightBlue).add_modifier(Modifier::BOLD);
+    let line = |key: &str, name: &str, desc: &str| {
+        ListItem::new(Spans::from(vec![
+            Span::styled(format!(" {:<8}", key), key_style),
+            Span::raw(format!("{:<18}", name)),
+            Span::styled(desc.to_string(), Style::default().fg(Color::Gray)),
+        ]))
+    };
+
+    let mut items = Action::builtins()
+        .iter()
+        .map(|a| line(a.key_hint(), a.name(), a.description()))
+        .collect::<Vec<_>>();
+
+    if !app.config.commands.is_empty() {
+        items.push(ListItem::new(""));
+        items.push(ListItem::new(Span::styled(" Commands", key_style)));
+        items.extend(app.config.commands
+            .iter()
+            .map(|c| line(c.key.as_deref().unwrap_or(""), &c.name, &c.description)));
+    }
+
+    let list = List::new(items).block(
+        Block::default()
+            .title(Span::styled(" Help ", Style::default().add_modifier(Modifier::BOLD)))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain)
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
+
+/// The details of the file under the cursor laid out like `stat`
+fn render_info<B: Backend>(f: &mut Frame<B>, app: &App) {
+    let file = match app.selected_file() {
+        Some(file) => file,
+        None => return,
+    };
+    let name = match &file.target {
+        Some(target) => format!("{} -> {}", file.name, target.display()),
+        None => file.name.clone(),
+    };
+    let kind = match file.ftype {
+        FileType::Directory => "directory",
+        FileType::File => "regular file",
+        FileType::Symlink => "symbolic link",
+        FileType::Executable => "executable",
+    };
+    let time = |time: Option<SystemTime>| time.map(time::full).unwrap_or_else(|| String::from("?"));
+
+    let key_style = Style::default().fg(Color::LightBlue).add_modifier(Modifier::BOLD);
+    let items = [
+        ("File", name),
+        ("Type", kind.to_string()),
+        ("Size", format!("{} ({})", file.size, file.size_to_readable())),
+        ("Mode", format!("{} ({})", file.perms.octal(), file.perms)),
+        ("You", access(file, &app.identity)),
+        ("Owner", format!("{} ({})", app.users.user(file.uid), file.uid)),
+        ("Group", format!("{} ({})", app.users.group(file.gid), file.gid)),
+        ("Links", file.links.to_string()),
+        ("Inode", file.inode.to_string()),
+        ("Device", format!("{:x}h/{}d", file.device, file.device)),
+        ("Access", time(file.accessed)),
+        ("Modify", time(file.modified)),
+        ("Change", time(file.changed)),
+        ("Birth", time(file.created)),
+    ]
+    .into_iter()
+    .map(|(key, value)| {
+        ListItem::new(Spans::from(vec![
+            Span::styled(format!(" {:>7}: ", key), key_style),
+            Span::raw(value),
+        ]))
+    })
+    .collect::<Vec<_>>();
+
+    // Tall enough for every line and the borders where there's room
+    let rows = f.size().height.max(1) as usize;
+    let height = ((items.len() + 2) * 100).div_ceil(rows).min(100);
+    let area = centered_rect(70, height as u16, f.size());
+
+    let list = List::new(items).block(
+        Block::default()
+            .title(Span::styled(" Info ", Style::default().add_modifier(Modifier::BOLD)))
+            .borders(Borders::ALL)
+            .border_type(BorderType::Plain)
+    );
+
+    f.render_widget(Clear, area);
+    f.render_widget(list, area);
+}
+
+/// What the user can do to file, like `read, write`
+fn access(file: &File, user: &Identity) -> String {
+    let allowed = [(Access::Read, "read"), (Access::Write, "write"), (Access::Execute, "execute")]
+        .into_iter()
+        .filter(|(access, _)| file.perms.allows(user, file.uid, file.gid, *access))
+        .map(|(_, name)| name)
+        .collect::<Vec<_>>();
+
+    match allowed.is_empty() {
+        true => String::from("no access"),
+        false => allowed.join(", "),
+    }
+}
+
+/// Returns a Rect of the given percentages centered in area
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(tui::layout::Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ].as_ref()
+        ).split(area);
+
+    Layout::default()
+        .direction(tui::layout::Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ].as_ref()
+        ).split(vertical[1])[1]
+}
+
 fn gen_file_preview<'a>(file: &File) -> anyhow::Result<Paragraph<'a>, String> {
+    preview_text(file).map(|s| Paragraph::new(Text::from(s)).block(prev_block()))
+}
+
+/// The start of the file that's previewed
+fn preview_text(file: &File) -> anyhow::Result<String, String> {
     use std::io::{Read, ErrorKind};
     use std::fs;
 
     // check to see if the file needs to be cut off
     // for performance reasons 
-    if file.size > 1000 {
+    if file.size > PREVIEW_BYTES as u64 {
         // create buffer
-        let mut buf = [0; 1000];
+        let mut buf = vec![0; PREVIEW_BYTES];
         let mut f = fs::File::open(file.path()).unwrap();
         // check to make sure everything read into the buffer properly
         match f.read_exact(&mut buf) {
@@ -102,43 +357,46 @@ fn gen_file_preview<'a>(file: &File) -> anyhow::Result<Paragraph<'a>, String> {
                 _ => return Err(e.to_string())
             }
         }
-        // return result of trying to create a string from buf
-        return match String::from_utf8(buf.to_vec()) {
-            Ok(s) => Ok(Paragraph::new(Text::from(s)).block(prev_block())),
+        // return result of trying to create a string from buf,
+        // the buffer may end in the middle of a character
+        return match std::str::from_utf8(&buf) {
+            Ok(s) => Ok(s.to_string()),
+            Err(e) if e.error_len().is_none() => Ok(String::from_utf8_lossy(&buf[..e.valid_up_to()]).into_owned()),
             Err(_) => Err("Invalid UTF-8".to_string())
         }
     }
 
     // if the file is less than 500 bytes just read_to_string
-    match std::fs::read_to_string(&file.path()) {
+    match std::fs::read_to_string(file.path()) {
         Ok(s) => {
             if s.is_empty() {
                 Err("Empty File".to_string())
             } else {
-                Ok(Paragraph::new(Text::from(s)).block(prev_block()))
+                Ok(s)
             }
         },
         Err(_) => Err("Invalid UTF-8".to_string()),
     }
 }
 
-fn gen_input(input: &str) -> Paragraph {
+fn gen_input(input: &str) -> Paragraph<'_> {
     Paragraph::new(input).alignment(Alignment::Left)
         .block(Block::default().borders(Borders::TOP))
 }
 
-fn invalid_prev(msg: &str) -> Paragraph {
+fn invalid_prev(msg: &str) -> Paragraph<'_> {
     Paragraph::new(Span::styled(msg, Style::default().fg(Color::Red)))
         .block(prev_block())
 }
 
-fn gen_dir_preview(file: &File) -> anyhow::Result<List, &str> {
-    match WorkingDir::get_files(file.path()) {
+fn gen_dir_preview<'a>(file: &File, app: &App, scroll: usize) -> anyhow::Result<List<'a>, &'a str> {
+    match app.wd.get_files(file.path()) {
         Ok(files) => {
             if files.is_empty() {
                 return Err("Empty Directory")
             }
-            Ok(List::new(list_from_files(&files)).block(prev_block()))
+            let files = &files[scroll.min(files.len() - 1)..];
+            Ok(List::new(list_from_files(files, &app.selection)).block(prev_block()))
         },
         Err(e) => match e.kind() {
             std::io::ErrorKind::PermissionDenied => Err("Permission Denied"),
@@ -147,7 +405,62 @@ fn gen_dir_preview(file: &File) -> anyhow::Result<List, &str> {
     }
 }
 
-fn gen_list<'a>(files: &'a [ListItem], selected_file: &File) -> List<'a> {
+/// Draws the inactive pane and the paths above both panes in dual pane mode
+fn render_other_pane<B: Backend>(f: &mut Frame<B>, app: &App, areas: &Areas) {
+    let (pane, area) = match (&app.pane, areas.other) {
+        (Some(pane), Some(area)) => (pane, area),
+        _ => return,
+    };
+
+    let above = |area: Rect| Rect::new(area.x, area.y - 1, area.width, 1);
+    f.render_widget(gen_pane_title(app.wd.cwd(), true), above(areas.list));
+    f.render_widget(gen_pane_title(pane.tab.wd.cwd(), false), above(area));
+
+    let files = list_from_files(&pane.tab.displayed_files, &pane.selection);
+    let mut state = ListState::default();
+    state.select(pane.tab.flist_state.selected());
+    f.render_stateful_widget(gen_list(&files, Color::DarkGray), area, &mut state);
+}
+
+fn gen_pane_title<'a>(cwd: &Path, active: bool) -> Paragraph<'a> {
+    let style = match active {
+        true => Style::default().add_modifier(Modifier::BOLD).fg(Color::LightBlue),
+        false => Style::default().fg(Color::White),
+    };
+
+    Paragraph::new(Span::styled(cwd.display().to_string(), style))
+        .alignment(Alignment::Center)
+}
+
+/// Lists the parent directory with the current directory highlighted
+fn render_parent<B: Backend>(f: &mut Frame<B>, app: &App, selection: &BTreeSet<PathBuf>, area: Rect) {
+    let cwd = app.wd.cwd();
+    let block = Block::default()
+        .borders(Borders::LEFT)
+        .style(Style::default().fg(Color::White))
+        .border_type(BorderType::Plain);
+
+    let files = match cwd.parent().map(|parent| app.wd.get_files(parent)) {
+        Some(Ok(files)) => files,
+        _ => return f.render_widget(block, area),
+    };
+
+    let mut state = ListState::default();
+    state.select(files.iter().position(|file| file.path() == cwd));
+
+    let items = list_from_files(&files, selection);
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(
+            Style::default()
+                .bg(Color::LightBlue)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD)
+        );
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+fn gen_list<'a>(files: &'a [ListItem], highlight: Color) -> List<'a> {
     let list_block = Block::default()
         .borders(Borders::RIGHT | Borders::LEFT)
         .style(Style::default().fg(Color::White))
@@ -159,7 +472,7 @@ fn gen_list<'a>(files: &'a [ListItem], selected_file: &File) -> List<'a> {
         .block(list_block)
         .highlight_style(
             Style::default()
-                .bg(selected_file.color())
+                .bg(highlight)
                 .fg(Color::Black)
                 .add_modifier(Modifier::BOLD)
         );
@@ -169,9 +482,10 @@ fn gen_list<'a>(files: &'a [ListItem], selected_file: &File) -> List<'a> {
 
 fn gen_extras<'a>(
     file: &File, 
+    users: &Users,
     selected: usize, 
     total: usize)
-    -> (Paragraph<'a>, Paragraph<'a>, Paragraph<'a>) 
+    -> (Paragraph<'a>, Paragraph<'a>, Paragraph<'a>, Paragraph<'a>) 
 {
     let mut color = Color::White;
     if !file.perms.is_valid() { color = Color::Red }
@@ -190,25 +504,86 @@ fn gen_extras<'a>(
         .alignment(Alignment::Center);
 
     let p3 = Paragraph::new(format!("{}/{}", selected + 1, total))
+        .style(Style::default().fg(color)).block(block.clone())
+        .alignment(Alignment::Center);
+
+    let modified = file.modified.map(time::short).unwrap_or_default();
+    let p4 = Paragraph::new(format!("{}:{} {}", users.user(file.uid), users.group(file.gid), modified))
         .style(Style::default().fg(color)).block(block)
         .alignment(Alignment::Center);
         
-    (p1, p2, p3)
+    (p1, p2, p3, p4)
 }
 
-fn gen_cwd<'a>(cwd: &Path) -> Paragraph<'a> {
-    Paragraph::new(Span::raw(cwd.display().to_string()))
+/// Shows the keys typed so far, the mode, how many files are
+/// selected across all directories and what's in the clipboard
+fn gen_status<'a>(app: &App, selected: usize) -> Paragraph<'a> {
+    let mut spans = vec![];
+    let pending = app.keys.pending();
+    if !pending.is_empty() {
+        spans.push(Span::raw(format!("{} ", pending)));
+    }
+
+    if app.input_mode == InputMode::Visual {
+        spans.push(Span::styled("-- VISUAL -- ", Style::default().add_modifier(Modifier::BOLD)));
+    }
+
+    if selected > 0 {
+        spans.push(Span::styled(format!("{} selected ", selected), Style::default().fg(SELECTED)));
+    }
+
+    let clipboard = &app.clipboard;
+    if !clipboard.files.is_empty() {
+        let what = if clipboard.cut { "cut" } else { "yanked" };
+        spans.push(Span::styled(format!("{} {} ", clipboard.files.len(), what), Style::default().fg(Color::Yellow)));
+    }
+
+    let sort = app.wd.sort();
+    if sort != Sort::default() {
+        let mut names = vec![sort.by.name()];
+        if sort.reverse {
+            names.push("reversed");
+        }
+        if sort.dirs_first {
+            names.push("dirs first");
+        }
+        spans.push(Span::raw(format!("sort: {} ", names.join(", "))));
+    }
+
+    Paragraph::new(Spans::from(spans))
+        .alignment(Alignment::Right)
+        .block(Block::default().borders(Borders::TOP))
+}
+
+/// The cwd with the tab bar along the top border when there's more than one tab
+fn gen_cwd<'a>(app: &App) -> Paragraph<'a> {
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .border_type(BorderType::Plain);
+
+    if app.tab_count() > 1 {
+        let tabs = app.tab_names()
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let style = match i == app.tab {
+                    true => Style::default().bg(Color::LightBlue).fg(Color::Black),
+                    false => Style::default(),
+                };
+                Span::styled(format!(" {}:{} ", i + 1, name), style)
+            })
+            .collect::<Vec<_>>();
+        block = block.title(Spans::from(tabs));
+    }
+
+    Paragraph::new(Span::raw(app.wd.cwd().display().to_string()))
         .style(Style::default().add_modifier(Modifier::BOLD).fg(Color::LightBlue))
         .alignment(Alignment::Center)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
-                .border_type(BorderType::Plain),            
-    )
+        .block(block)
 }
 
-fn gen_err(msg: &str) -> Paragraph {
+fn gen_err(msg: &str) -> Paragraph<'_> {
     Paragraph::new(msg)
         .style(Style::default().fg(Color::Red)
                .add_modifier(Modifier::BOLD))
@@ -227,45 +602,153 @@ Block::default()
     .border_type(BorderType::Plain)
 }
 
-fn list_from_files<'a>(files: &[File]) -> Vec<ListItem<'a>> {
+/// Selected files stand out from the colors of every file type
+fn file_color(file: &File, selection: &BTreeSet<PathBuf>) -> Color {
+    if selection.contains(file.path()) {
+        SELECTED
+    } else {
+        file.color()
+    }
+}
+
+fn list_from_files<'a>(files: &[File], selection: &BTreeSet<PathBuf>) -> Vec<ListItem<'a>> {
   files
     .iter()
     .map(|f| {
         ListItem::new(
             Span::styled(f.name.clone(),
-            Style::default().fg(f.color()))
+            Style::default().fg(file_color(f, selection)))
         )
     })
     .collect::<Vec<_>>()
 }
 
+/// Lists the files in range of the cwd's listing, indented under their
+/// directories in tree mode and after the long listing's columns in width
+fn list_from_listing<'a>(
+    app: &App,
+    selection: &BTreeSet<PathBuf>,
+    range: Range<usize>,
+    width: usize)
+    -> Vec<ListItem<'a>>
+{
+    let files = &app.displayed_files[range.clone()];
+    let guides = match app.tree {
+        true => tree_guides(&app.displayed_files).drain(range).collect(),
+        false => vec![String::new(); files.len()],
+    };
+    let lines = match app.long {
+        true => long::lines(files, &app.config.long_columns, &app.users, width),
+        false => Vec::new(),
+    };
+
+    files
+        .iter()
+        .zip(guides)
+        .enumerate()
+        .map(|(i, (f, guide))| {
+            let mut spans = Vec::new();
+            let name = match lines.get(i) {
+                Some(line) => {
+                    let room = width.saturating_sub(line.info.chars().count() + guide.chars().count());
+                    spans.push(Span::styled(line.info.clone(), Style::default().fg(Color::Gray)));
+                    long::truncate(&format!("{}{}", f.name, line.target), room)
+                },
+                None => f.name.clone(),
+            };
+            spans.push(Span::styled(guide, Style::default().fg(Color::DarkGray)));
+            spans.push(Span::styled(name, Style::default().fg(file_color(f, selection))));
+            ListItem::new(Spans::from(spans))
+        })
+        .collect()
+}
+
+/// The guides drawn before each file in the tree. They're worked out from
+/// the bottom up since they depend on which files are last in their directory
+fn tree_guides(files: &[File]) -> Vec<String> {
+    // Whether there's a file further down at each depth before a shallower one
+    let mut more = Vec::new();
+    let mut guides = vec![String::new(); files.len()];
+
+    for (i, file) in files.iter().enumerate().rev() {
+        let depth = file.depth;
+        more.resize(depth + 1, false);
+        if depth > 0 {
+            let guide = &mut guides[i];
+            for &more in &more[1..depth] {
+                guide.push_str(if more { "│  " } else { "   " });
+            }
+            guide.push_str(if more[depth] { "├─ " } else { "└─ " });
+        }
+        more[depth] = true;
+    }
+
+    guides
+}
+
 fn render_empty<B: Backend>(f: &mut Frame<B>, app: &mut App, user_inp: &mut Box<dyn Input>) {
-    let (chunks, _) = gen_chunks(f);
-    f.render_widget(gen_cwd(app.wd.cwd()), chunks[0]);
+    let areas = areas(app, f.size());
+    f.render_widget(gen_cwd(app), areas.header);
+    render_other_pane(f, app, &areas);
+
+    // The message takes up everything right of the parent column
+    let mut body = areas.middle;
+    if let Some(parent) = areas.parent {
+        render_parent(f, app, &app.selection, parent);
+        body.x += parent.width;
+        body.width -= parent.width;
+    }
+    if areas.other.is_some() {
+        body = areas.list;
+    }
 
     match app.input_mode {
         InputMode::Normal => {
-            f.render_widget(gen_err("Empty Directory"), chunks[1]);
-            f.render_widget(gen_input(""), chunks[2])
+            f.render_widget(gen_err("Empty Directory"), body);
+            f.render_widget(gen_input(""), areas.footer)
         },
         InputMode::Editing => {
             let msg = if app.is_searching {
                 format!("Pattern not found: {}", user_inp.input())
             } else { String::new() };
 
-            f.render_widget(gen_input(&user_inp.output()), chunks[2]);
-            f.set_cursor(chunks[2].x + user_inp.output().len() as u16, chunks[2].y + 1);
-            f.render_widget(gen_err(&msg), chunks[1]);
+            f.render_widget(gen_input(&user_inp.output()), areas.footer);
+            f.set_cursor(areas.footer.x + user_inp.output().len() as u16, areas.footer.y + 1);
+            f.render_widget(gen_err(&msg), body);
         },
         InputMode::Error => {
-            f.render_widget(gen_err("Empty Directory"), chunks[1]);
-            f.render_widget(gen_err(&app.err_msg), chunks[2])
+            f.render_widget(gen_err("Empty Directory"), body);
+            f.render_widget(gen_err(&app.err_msg), areas.footer)
         },
         _ => {},
     }
 }
 
-fn gen_chunks<B: Backend>(f: &mut Frame<B>) -> (Vec<Rect>, Vec<Rect>) {
+/// Where each part of the UI is drawn
+pub struct Areas {
+    pub header: Rect,
+    /// Everything between the header and footer
+    pub middle: Rect,
+    pub parent: Option<Rect>,
+    pub list: Rect,
+    pub preview: Option<Rect>,
+    /// The inactive pane in dual pane mode
+    pub other: Option<Rect>,
+    pub footer: Rect,
+}
+
+/// The layout for the mode fm is in
+pub fn areas(app: &App, size: Rect) -> Areas {
+    match &app.pane {
+        Some(pane) => dual_layout(size, pane.left),
+        None => layout(size, &app.config.columns),
+    }
+}
+
+/// Splits the screen into the header, the parent, current and preview
+/// columns sized by their ratios, and the footer. Narrow terminals drop
+/// the parent column and then the preview
+pub fn layout(area: Rect, columns: &[u16; 3]) -> Areas {
     let chunks = Layout::default()
         .direction(tui::layout::Direction::Vertical)
         .margin(0)
@@ -275,19 +758,64 @@ fn gen_chunks<B: Backend>(f: &mut Frame<B>) -> (Vec<Rect>, Vec<Rect>) {
                 Constraint::Min(3),
                 Constraint::Length(2),
             ].as_ref()
-        ).split(f.size());
+        ).split(area);
 
+    let [mut parent, list, mut preview] = *columns;
+    if area.width < PARENT_MIN_WIDTH {
+        parent = 0;
+    }
+    if area.width < PREVIEW_MIN_WIDTH {
+        preview = 0;
+    }
+
+    // Summed as u32 so big ratios can't overflow
+    let total = parent as u32 + list as u32 + preview as u32;
+    let ratios = [parent, list, preview];
+    let constraints = ratios
+        .iter()
+        .map(|&r| Constraint::Ratio(r as u32, total))
+        .collect::<Vec<_>>();
     let middle_chunks = Layout::default()
         .direction(tui::layout::Direction::Horizontal)
-        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
-        .split(chunks[1]); 
+        .constraints(constraints)
+        .split(chunks[1]);
+
+    // Hidden columns still get a chunk, just an empty one
+    Areas {
+        header: chunks[0],
+        middle: chunks[1],
+        parent: (parent > 0).then_some(middle_chunks[0]),
+        list: middle_chunks[1],
+        preview: (preview > 0).then_some(middle_chunks[2]),
+        other: None,
+        footer: chunks[2],
+    }
+}
 
-    
+/// Splits the middle into two panes of the same size, each
+/// with its path on the line above it
+pub fn dual_layout(area: Rect, other_left: bool) -> Areas {
+    let mut areas = layout(area, &[0, 1, 0]);
+    let halves = Layout::default()
+        .direction(tui::layout::Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(areas.middle);
+
+    let panes = halves
+        .iter()
+        .map(|half| Rect::new(half.x, half.y + 1, half.width, half.height.saturating_sub(1)))
+        .collect::<Vec<_>>();
 
-    (chunks, middle_chunks)
+    let (list, other) = match other_left {
+        true => (panes[1], panes[0]),
+        false => (panes[0], panes[1]),
+    };
+    areas.list = list;
+    areas.other = Some(other);
+    areas
 }
 
-fn nmode_extra_chunks(chunks: &[Rect]) -> Vec<Rect> {
+fn nmode_extra_chunks(footer: Rect) -> Vec<Rect> {
      Layout::default()
         .direction(tui::layout::Direction::Horizontal)
         .constraints(
@@ -296,8 +824,156 @@ fn nmode_extra_chunks(chunks: &[Rect]) -> Vec<Rect> {
                  Constraint::Percentage(13),
                  Constraint::Percentage(13),
                  Constraint::Percentage(13),
-                 Constraint::Percentage(60),
+                 Constraint::Percentage(25),
+                 Constraint::Percentage(35),
             ].as_ref()
         )
-        .split(chunks[2])
+        .split(footer)
+}
+
+/// The file in the list at the given screen position, if any
+pub fn list_index_at(app: &App, size: Rect, column: u16, row: u16) -> Option<usize> {
+    let list = areas(app, size).list;
+    if !contains(list, column, row) {
+        return None
+    }
+
+    let i = app.scroll + (row - list.y) as usize;
+    (i < app.displayed_files.len()).then_some(i)
+}
+
+/// Returns true if the position is on the preview
+pub fn in_preview(app: &App, size: Rect, column: u16, row: u16) -> bool {
+    match areas(app, size).preview {
+        Some(preview) => contains(preview, column, row),
+        None => false,
+    }
+}
+
+/// How many lines the preview of the file under the cursor has
+pub fn preview_lines(app: &App) -> usize {
+    let file = match app.selected_file() {
+        Some(file) => file,
+        None => return 0,
+    };
+
+    if let Some((path, Some(preview))) = &app.preview {
+        if path == file.path() {
+            return preview.lines().count()
+        }
+    }
+
+    match file.ftype {
+        FileType::Directory => app.wd.get_files(file.path()).map_or(0, |files| files.len()),
+        FileType::File => preview_text(file).map_or(0, |s| s.lines().count()),
+        _ => 0,
+    }
+}
+
+/// Returns true if the position is on the inactive pane
+pub fn in_other_pane(app: &App, size: Rect, column: u16, row: u16) -> bool {
+    match areas(app, size).other {
+        Some(other) => contains(Rect::new(other.x, other.y - 1, other.width, other.height + 1), column, row),
+        None => false,
+    }
+}
+
+/// The ancestor of cwd whose name is at the given position in the header
+pub fn cwd_ancestor_at(cwd: &Path, size: Rect, column: u16, row: u16) -> Option<PathBuf> {
+    let header = layout(size, &[0, 1, 0]).header;
+    if row != header.y + 1 || !contains(header, column, row) {
+        return None
+    }
+
+    // The path is centered between the borders like Paragraph does
+    let text = cwd.display().to_string();
+    let width = header.width.saturating_sub(2);
+    let start = header.x + 1 + (width / 2).saturating_sub(text.chars().count() as u16 / 2);
+    let i = column.checked_sub(start)? as usize;
+    if i >= text.chars().count() {
+        return None
+    }
+
+    // Everything up to the end of the name that was clicked
+    let end = text.char_indices()
+        .skip(i)
+        .find(|(_, c)| *c == '/')
+        .map(|(end, _)| end)
+        .unwrap_or(text.len());
+
+    match &text[..end] {
+        "" => Some(PathBuf::from("/")),
+        ancestor => Some(PathBuf::from(ancestor)),
+    }
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.x..area.x + area.width).contains(&column) && (area.y..area.y + area.height).contains(&row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cwd_ancestor_at, dual_layout, layout, tree_guides};
+    use crate::file::File;
+    use std::path::{Path, PathBuf};
+    use tui::layout::Rect;
+
+    #[test]
+    fn test_cwd_ancestor_at() {
+        // "/home/fm" is drawn starting at column 1 + 9 - 4 = 6
+        let size = Rect::new(0, 0, 20, 10);
+        let cwd = Path::new("/home/fm");
+
+        assert_eq!(cwd_ancestor_at(cwd, size, 6, 1), Some(PathBuf::from("/")));
+        assert_eq!(cwd_ancestor_at(cwd, size, 8, 1), Some(PathBuf::from("/home")));
+        assert_eq!(cwd_ancestor_at(cwd, size, 12, 1), Some(PathBuf::from("/home/fm")));
+        assert_eq!(cwd_ancestor_at(cwd, size, 14, 1), None);
+        assert_eq!(cwd_ancestor_at(cwd, size, 8, 0), None);
+    }
+
+    #[test]
+    fn test_layout_collapses() {
+        let wide = layout(Rect::new(0, 0, 120, 20), &[1, 3, 4]);
+        let (parent, preview) = (wide.parent.unwrap(), wide.preview.unwrap());
+        assert_eq!(parent.width + wide.list.width + preview.width, 120);
+        assert_eq!(wide.list.x, parent.x + parent.width);
+        assert!(parent.width < wide.list.width && wide.list.width < preview.width);
+
+        let medium = layout(Rect::new(0, 0, 60, 20), &[1, 3, 4]);
+        assert!(medium.parent.is_none());
+        assert_eq!(medium.list.width + medium.preview.unwrap().width, 60);
+
+        let narrow = layout(Rect::new(0, 0, 45, 20), &[1, 3, 4]);
+        assert!(narrow.parent.is_none() && narrow.preview.is_none());
+        assert_eq!(narrow.list.width, 45);
+
+        // A ratio of 0 hides a column on any terminal
+        assert!(layout(Rect::new(0, 0, 120, 20), &[0, 1, 1]).parent.is_none());
+
+        let huge = layout(Rect::new(0, 0, 120, 20), &[u16::MAX, u16::MAX, u16::MAX]);
+        assert_eq!(huge.parent.unwrap().width + huge.list.width + huge.preview.unwrap().width, 120);
+    }
+
+    #[test]
+    fn test_dual_layout() {
+        let areas = dual_layout(Rect::new(0, 0, 100, 20), false);
+        let other = areas.other.unwrap();
+        assert_eq!((areas.list.x, other.x), (0, 50));
+        // The line above each pane shows its path
+        assert_eq!(areas.list.y, areas.middle.y + 1);
+        assert!(areas.parent.is_none() && areas.preview.is_none());
+
+        let areas = dual_layout(Rect::new(0, 0, 100, 20), true);
+        assert_eq!((areas.list.x, areas.other.unwrap().x), (50, 0));
+    }
+
+    #[test]
+    fn test_tree_guides() {
+        let files = [("a", 0), ("b", 1), ("c", 2), ("d", 1), ("e", 2), ("f", 0)]
+            .iter()
+            .map(|(name, depth)| File { depth: *depth, ..File::from(PathBuf::from(name)) })
+            .collect::<Vec<_>>();
+
+        assert_eq!(tree_guides(&files), vec!["", "├─ ", "│  └─ ", "└─ ", "   └─ ", ""]);
+    }
 }
@@ -0,0 +1,312 @@
+use super::{App, Input};
+use crate::permissions::Permissions;
+
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use crossterm::event::KeyCode;
+
+/// Which files inside directories are changed too
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Recursive {
+    Off,
+    All,
+    Files,
+    Dirs,
+}
+
+impl Recursive {
+    fn next(self) -> Self {
+        match self {
+            Recursive::Off => Recursive::All,
+            Recursive::All => Recursive::Files,
+            Recursive::Files => Recursive::Dirs,
+            Recursive::Dirs => Recursive::Off,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Recursive::Off => "no",
+            Recursive::All => "everything",
+            Recursive::Files => "only files",
+            Recursive::Dirs => "only directories",
+        }
+    }
+}
+
+/// Changes the mode of files to an octal or symbolic mode typed in,
+/// or built up by toggling bits of the mode of the first file
+pub struct Chmod {
+    msg: String,
+    input: String,
+    files: Vec<PathBuf>,
+    // The mode of the first file, shown before and after the change
+    before: Permissions,
+    // Which of the nine rwx bits space toggles
+    cursor: usize,
+    recursive: Recursive,
+}
+
+impl Chmod {
+    /// The files to change, the mode shown is the first one's
+    pub fn files(mut self, files: Vec<PathBuf>) -> Self {
+        if files.len() > 1 {
+            self.msg = format!(" chmod {} files: ", files.len());
+        }
+        if let Some(mdata) = files.first().and_then(|f| fs::metadata(f).ok()) {
+            self.before = Permissions::from(mdata.mode());
+        }
+        self.files = files;
+        self
+    }
+
+    /// The mode of the first file with what's been typed applied
+    fn after(&self) -> Option<Permissions> {
+        self.before.apply(&self.input)
+    }
+
+    /// Adds a clause flipping the bit under the cursor
+    fn toggle(&mut self) {
+        let after = match self.after() {
+            Some(after) => after,
+            None => return,
+        };
+
+        let class = ['u', 'g', 'o'][self.cursor / 3];
+        let perm = ['r', 'w', 'x'][self.cursor % 3];
+        let op = match after.mode() & (0o400 >> self.cursor) {
+            0 => '+',
+            _ => '-',
+        };
+
+        if !self.input.is_empty() {
+            self.input.push(',');
+        }
+        self.input.extend([class, op, perm]);
+    }
+
+    /// Adds the file and, when recursing, the files inside it. The files
+    /// given are always changed, recursion only picks what's inside them
+    fn paths(&self, path: &Path, given: bool, paths: &mut Vec<PathBuf>) {
+        let is_dir = path.is_dir();
+        let wanted = match self.recursive {
+            _ if given => true,
+            Recursive::Off | Recursive::All => true,
+            Recursive::Files => !is_dir,
+            Recursive::Dirs => is_dir,
+        };
+        if wanted {
+            paths.push(path.to_owned());
+        }
+
+        if self.recursive == Recursive::Off || !is_dir {
+            return
+        }
+
+        // Symlinks inside are left alone, changing one changes what it points to
+        if let Ok(entries) = fs::read_dir(path) {
+            for entry in entries.flatten() {
+                if entry.file_type().is_ok_and(|t| !t.is_symlink()) {
+                    self.paths(&entry.path(), false, paths);
+                }
+            }
+        }
+    }
+}
+
+impl Default for Chmod {
+    fn default() -> Self {
+        Self {
+            msg: " chmod: ".to_string(),
+            input: String::with_capacity(10),
+            files: Vec::new(),
+            before: Permissions::from(0),
+            cursor: 0,
+            recursive: Recursive::Off,
+        }
+    }
+}
+
+impl Input for Chmod {
+    fn on_enter(&mut self, app: &mut App) {
+        if self.input.is_empty() {
+            return app.to_normal_mode()
+        }
+        if self.after().is_none() {
+            return app.err(&format!("Invalid mode: {}", self.input))
+        }
+        app.to_normal_mode();
+
+        let mut paths = Vec::new();
+        for file in &self.files {
+            self.paths(file, true, &mut paths);
+        }
+
+        // Each file that can't be changed is reported with why
+        let mut errors = Vec::new();
+        for path in &paths {
+            let result = fs::metadata(path).and_then(|mdata| {
+                // Each file keeps the bits the mode doesn't set
+                let mode = Permissions::from(mdata.mode()).apply(&self.input).unwrap().mode();
+                fs::set_permissions(path, fs::Permissions::from_mode(mode))
+            });
+            if let Err(e) = result {
+                let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+                errors.push(format!("{}: {}", name, e));
+            }
+        }
+
+        app.reload();
+        app.err_files(&errors, paths.len());
+    }
+
+    fn add_to_input(&mut self, ch: char, _: &mut App) {
+        match ch {
+            ' ' => self.toggle(),
+            _ => self.input.push(ch),
+        }
+    }
+
+    fn on_key(&mut self, key: KeyCode, _: &mut App) {
+        match key {
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(8),
+            _ => {}
+        }
+    }
+
+    fn del(&mut self, app: &mut App) {
+        if !self.input.is_empty() {
+            self.input.pop();
+        } else {
+            app.to_normal_mode()
+        }
+    }
+
+    /// Tab changes what's done inside directories
+    fn complete(&mut self, _: &mut App) {
+        self.recursive = self.recursive.next();
+    }
+
+    fn completions(&self, _: &App) -> Vec<(String, String)> {
+        let after = match self.after() {
+            Some(after) => format!("{} {}", after, after.octal()),
+            None => String::from("invalid mode"),
+        };
+
+        // The caret sits under the bit, after the file type
+        vec![
+            ("before".to_string(), format!("{} {}", self.before, self.before.octal())),
+            ("after".to_string(), after),
+            (String::new(), format!("{:>1$}", "^", self.cursor + 2)),
+            ("recursive".to_string(), self.recursive.name().to_string()),
+            ("keys".to_string(), "left/right pick a bit, space flips it, tab changes recursion".to_string()),
+        ]
+    }
+
+    fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    fn input(&self) -> &str {
+        &self.input
+    }
+
+    fn clear(&mut self) {
+        self.input.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Chmod, Input, Recursive};
+    use crate::app::App;
+    use crate::config::Config;
+    use crate::permissions::Permissions;
+    use std::fs;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+    use std::path::{Path, PathBuf};
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fm-chmod-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("sub/deep")).unwrap();
+        for file in ["a", "sub/b"] {
+            fs::write(dir.join(file), "").unwrap();
+            fs::set_permissions(dir.join(file), fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        dir
+    }
+
+    fn mode(path: &Path) -> u32 {
+        fs::metadata(path).unwrap().mode() & 0o7777
+    }
+
+    #[test]
+    fn test_toggle() {
+        let mut chmod = Chmod { before: Permissions::from(0o100644), ..Chmod::default() };
+        chmod.cursor = 2;
+        chmod.toggle();
+        assert_eq!(chmod.input, "u+x");
+        chmod.cursor = 4;
+        chmod.toggle();
+        chmod.cursor = 2;
+        chmod.toggle();
+        assert_eq!(chmod.input, "u+x,g+w,u-x");
+        assert_eq!(chmod.after(), Some(Permissions::from(0o100664)));
+
+        // Nothing to toggle from while the mode is invalid
+        chmod.input = String::from("u+q");
+        chmod.toggle();
+        assert_eq!(chmod.input, "u+q");
+    }
+
+    #[test]
+    fn test_octal_and_symbolic() {
+        let mut chmod = Chmod { before: Permissions::from(0o100644), ..Chmod::default() };
+        chmod.input = String::from("u=rwx,g=rx,o=");
+        let symbolic = chmod.after().unwrap();
+        assert_eq!(symbolic.to_string(), "-rwxr-x---");
+
+        // Typing the octal it shows gives the same mode back
+        chmod.input = symbolic.octal();
+        assert_eq!(chmod.after(), Some(symbolic));
+    }
+
+    #[test]
+    fn test_recursion() {
+        let dir = testing_dir("recursion");
+        let paths = |recursive| {
+            let chmod = Chmod { recursive, ..Chmod::default() }.files(vec![dir.join("sub"), dir.join("a")]);
+            let mut paths = Vec::new();
+            chmod.files.iter().for_each(|f| chmod.paths(f, true, &mut paths));
+            paths.sort();
+            paths
+        };
+
+        assert_eq!(paths(Recursive::Off), vec![dir.join("a"), dir.join("sub")]);
+        assert_eq!(paths(Recursive::All).len(), 4);
+        // The files given are changed whatever is picked inside them
+        assert_eq!(paths(Recursive::Files), vec![dir.join("a"), dir.join("sub"), dir.join("sub/b")]);
+        assert_eq!(paths(Recursive::Dirs), vec![dir.join("a"), dir.join("sub"), dir.join("sub/deep")]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_errors_per_file() {
+        let dir = testing_dir("errors");
+        let mut app = App::with_config(Some(&dir), Config::default()).unwrap();
+        let files = vec![dir.join("a"), dir.join("gone"), dir.join("lost")];
+        let mut chmod = Chmod::default().files(files);
+        "u+x".chars().for_each(|c| chmod.add_to_input(c, &mut app));
+        chmod.on_enter(&mut app);
+
+        assert_eq!(mode(&dir.join("a")), 0o744);
+        assert!(app.err_msg.starts_with("2 of 3 files weren't changed"), "{}", app.err_msg);
+        assert!(app.err_msg.contains("gone: ") && app.err_msg.contains("lost: "));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+use super::{Input, App};
+use std::fs;
+use std::os::unix::fs::{chown, lchown, MetadataExt};
+use std::path::{Path, PathBuf};
+
+// Completions shown at once
+const MAX_COMPLETIONS: usize = 10;
+
+/// Changes the owner and group of files given as `user`, `user:group`
+/// or `:group`. Starting with `-R ` changes everything inside directories
+pub struct FileChown<'a> {
+    msg: &'a str,
+    input: String,
+    files: Vec<PathBuf>,
+}
+
+impl<'a> FileChown<'a> {
+    pub fn files(mut self, files: Vec<PathBuf>) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Whether it's recursive and the owner without the flag
+    fn spec(&self) -> (bool, &str) {
+        match self.input.strip_prefix("-R ") {
+            Some(spec) => (true, spec.trim_start()),
+            None => (false, &self.input),
+        }
+    }
+}
+
+impl<'a> Default for FileChown<'a> {
+    fn default() -> Self {
+        Self { msg: " chown: ", input: String::with_capacity(20), files: Vec::new() }
+    }
+}
+
+/// Adds the file and, when recursing, the files inside it. Symlinks inside
+/// are changed themselves where the files given follow them
+fn paths(path: &Path, recursive: bool, follow: bool, paths: &mut Vec<(PathBuf, bool)>) {
+    paths.push((path.to_owned(), follow));
+
+    let is_dir = match follow {
+        true => path.is_dir(),
+        false => path.symlink_metadata().is_ok_and(|m| m.is_dir()),
+    };
+    if !recursive || !is_dir {
+        return
+    }
+
+    if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            self::paths(&entry.path(), recursive, false, paths);
+        }
+    }
+}
+
+impl<'a> Input for FileChown<'a> {
+    fn on_enter(&mut self, app: &mut App) {
+        let (recursive, spec) = self.spec();
+        let (user, group) = spec.split_once(':').unwrap_or((spec, ""));
+
+        let uid = match user {
+            "" => None,
+            name => match app.users.uid(name) {
+                Some(uid) => Some(uid),
+                None => return app.err(&format!("No user named {}", name)),
+            },
+        };
+        let gid = match group {
+            "" => None,
+            name => match app.users.gid(name) {
+                Some(gid) => Some(gid),
+                None => return app.err(&format!("No group named {}", name)),
+            },
+        };
+        app.to_normal_mode();
+        if uid.is_none() && gid.is_none() {
+            return
+        }
+
+        let mut targets = Vec::new();
+        for file in &self.files {
+            paths(file, recursive, true, &mut targets);
+        }
+
+        // Each file that can't be changed is reported with why
+        let mut errors = Vec::new();
+        for (path, follow) in &targets {
+            let mdata = match follow {
+                true => fs::metadata(path),
+                false => fs::symlink_metadata(path),
+            };
+
+            let result = match mdata {
+                Ok(mdata) if !app.identity.is_root() && mdata.uid() != app.identity.uid => {
+                    Err(String::from("not owned by you"))
+                },
+                Ok(_) if *follow => chown(path, uid, gid).map_err(|e| e.to_string()),
+                Ok(_) => lchown(path, uid, gid).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            };
+
+            if let Err(e) = result {
+                let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+                errors.push(format!("{}: {}", name, e));
+            }
+        }
+
+        app.reload();
+        app.err_files(&errors, targets.len());
+    }
+    
+    fn add_to_input(&mut self, ch: char, _: &mut App) {
+        self.input.push(ch);
+    }
+
+    fn del(&mut self, app: &mut App) {
+        if !self.input.is_empty() {
+            self.input.pop();
+        } else {
+            app.to_normal_mode()
+        }
+    }
+
+    /// Users before the colon and groups after it. Only root can give files
+    /// away so others only see the groups they're in
+    fn completions(&self, app: &App) -> Vec<(String, String)> {
+        let (_, spec) = self.spec();
+        let candidates = match spec.split_once(':') {
+            Some((_, group)) => app.users.groups()
+                .into_iter()
+                .filter(|(_, gid)| app.identity.is_root() || app.identity.in_group(*gid))
+                .filter(|(name, _)| name.starts_with(group))
+                .map(|(name, gid)| (name.to_string(), format!("gid {}", gid)))
+                .collect::<Vec<_>>(),
+            None => app.users.users()
+                .into_iter()
+                .filter(|(name, _)| name.starts_with(spec))
+                .map(|(name, uid)| (name.to_string(), format!("uid {}", uid)))
+                .collect(),
+        };
+
+        candidates.into_iter().take(MAX_COMPLETIONS).collect()
+    }
+
+    fn complete(&mut self, app: &mut App) {
+        let name = match self.completions(app).into_iter().next() {
+            Some((name, _)) => name,
+            None => return,
+        };
+
+        // Replaces the part being typed, the user or the group
+        let start = match self.input.rfind(':') {
+            Some(i) => i + 1,
+            None => self.input.len() - self.spec().1.len(),
+        };
+        self.input.truncate(start);
+        self.input.push_str(&name);
+    }
+
+    fn msg(&self) -> &str {
+        self.msg
+    }
+
+    fn input(&self) -> &str {
+        &self.input
+    }
+
+    fn clear(&mut self) {
+        self.input.clear();
+    } 
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileChown, Input};
+    use crate::app::{App, InputMode};
+    use crate::config::Config;
+    use crate::users::{Identity, Users};
+    use std::fs;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::{Path, PathBuf};
+
+    fn testing_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fm-chown-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a"), "").unwrap();
+        fs::write(dir.join("passwd"), "root:x:0:0::/root:/bin/sh\nme:x:1000:1000::/home/me:/bin/sh\nmail:x:8:8::/:/bin/sh\n").unwrap();
+        fs::write(dir.join("group"), "root:x:0:\nme:x:1000:\nmail:x:8:\n").unwrap();
+        dir
+    }
+
+    /// An App in dir knowing only the users in its passwd and group files
+    fn testing_app(dir: &Path, identity: Identity) -> App {
+        let mut app = App::with_config(Some(dir), Config::default()).unwrap();
+        app.users = Users::from_files(&dir.join("passwd"), &dir.join("group"));
+        app.identity = identity;
+        app
+    }
+
+    fn typed(input: &str, app: &mut App) -> FileChown<'static> {
+        let mut chown = FileChown::default();
+        input.chars().for_each(|c| chown.add_to_input(c, app));
+        chown
+    }
+
+    #[test]
+    fn test_spec() {
+        let dir = testing_dir("spec");
+        let mut app = testing_app(&dir, Identity::current());
+        assert_eq!(typed("-R  me:mail", &mut app).spec(), (true, "me:mail"));
+        assert_eq!(typed(":mail", &mut app).spec(), (false, ":mail"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_complete() {
+        let dir = testing_dir("complete");
+        let mut app = testing_app(&dir, Identity { uid: 0, gid: 0, groups: vec![] });
+
+        let mut chown = typed("m", &mut app);
+        assert_eq!(chown.completions(&app), vec![
+            ("mail".to_string(), "uid 8".to_string()),
+            ("me".to_string(), "uid 1000".to_string()),
+        ]);
+        chown.complete(&mut app);
+        assert_eq!(chown.input(), "mail");
+
+        let mut chown = typed("-R me:ro", &mut app);
+        chown.complete(&mut app);
+        assert_eq!(chown.input(), "-R me:root");
+
+        // Others only see the groups they're in
+        app.identity = Identity { uid: 1000, gid: 1000, groups: vec![8] };
+        let groups = typed(":", &mut app).completions(&app);
+        assert_eq!(groups.into_iter().map(|(name, _)| name).collect::<Vec<_>>(), vec!["mail", "me"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_errors_per_file() {
+        let dir = testing_dir("errors");
+        let owner = fs::metadata(dir.join("a")).unwrap().uid();
+        let mut app = testing_app(&dir, Identity::current());
+
+        let mut chown = typed("nobody", &mut app);
+        chown.on_enter(&mut app);
+        assert_eq!(app.err_msg, "No user named nobody");
+
+        // Changing a file to its own owner works, a missing one doesn't
+        app.to_normal_mode();
+        let mut chown = typed(&owner.to_string(), &mut app).files(vec![dir.join("a"), dir.join("gone")]);
+        chown.on_enter(&mut app);
+        assert!(app.err_msg.starts_with("gone: "), "{}", app.err_msg);
+
+        // Ids are taken as they are when no user or group has them as a name
+        app.to_normal_mode();
+        let group = fs::metadata(dir.join("a")).unwrap().gid();
+        let mut chown = typed(&format!("{}:{}", owner, group), &mut app).files(vec![dir.join("a")]);
+        chown.on_enter(&mut app);
+        assert_eq!(app.input_mode, InputMode::Normal, "{}", app.err_msg);
+        let mut chown = typed(":4294967296", &mut app);
+        chown.on_enter(&mut app);
+        assert_eq!(app.err_msg, "No group named 4294967296");
+
+        // Files someone else owns are refused unless fm runs as root
+        app.to_normal_mode();
+        app.identity = Identity { uid: owner + 1, gid: 0, groups: vec![] };
+        let mut chown = typed(&owner.to_string(), &mut app).files(vec![dir.join("a")]);
+        chown.on_enter(&mut app);
+        assert_eq!(app.err_msg, "a: not owned by you");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
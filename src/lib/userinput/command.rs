@@ -0,0 +1,79 @@
+use super::{App, Input};
+use crate::action::Action;
+
+pub struct CommandLine<'a> {
+    msg: &'a str,
+    input: String,
+}
+
+impl<'a> Default for CommandLine<'a> {
+    fn default() -> Self {
+        Self {
+            msg: ":",
+            input: String::with_capacity(15),
+        }
+    }
+}
+
+impl<'a> Input for CommandLine<'a> {
+    fn msg(&self) -> &'a str {
+        self.msg
+    }
+
+    fn input(&self) -> &str {
+        &self.input
+    }
+
+    fn on_enter(&mut self, app: &mut App) {
+        let name = self.input.trim().to_string();
+        app.to_normal_mode();
+        if name.is_empty() {
+            return
+        }
+
+        if let Err(e) = app.run_command(&name) {
+            app.err(&e)
+        }
+    }
+
+    fn add_to_input(&mut self, ch: char, _: &mut App) {
+        self.input.push(ch);
+    }
+
+    fn del(&mut self, app: &mut App) {
+        if !self.input.is_empty() {
+            self.input.pop();
+        } else {
+            app.to_normal_mode()
+        }
+    }
+
+    fn clear(&mut self) {
+        self.input.clear();
+    }
+
+    fn completions(&self, app: &App) -> Vec<(String, String)> {
+        if self.input.starts_with('!') {
+            return vec![]
+        }
+
+        let custom = app.config.commands
+            .iter()
+            .map(|c| (c.name.clone(), c.description.clone()));
+
+        let builtins = Action::builtins()
+            .into_iter()
+            .map(|a| (a.name().to_string(), a.description().to_string()));
+
+        custom
+            .chain(builtins)
+            .filter(|(name, _)| name.starts_with(self.input.trim()))
+            .collect()
+    }
+
+    fn complete(&mut self, app: &mut App) {
+        if let Some((name, _)) = self.completions(app).into_iter().next() {
+            self.input = name;
+        }
+    }
+}
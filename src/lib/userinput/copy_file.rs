@@ -36,7 +36,7 @@ impl<'a> Input for FileCopy<'a> {
 
         match fs::copy(app.selected_file().unwrap().path(), &new_file){
             Ok(_) => { 
-                app.select_file(&new_file);
+                app.show_file(&new_file);
                 app.to_normal_mode();
             },
             Err(e) => match e.kind() {
@@ -1,24 +1,38 @@
-use super::{App, FileType, Input};
+use super::{App, Input};
 
 use std::fs;
+use std::path::PathBuf;
 
 use std::io::ErrorKind;
 
-pub struct FileDelete <'a> {
-    msg: &'a str,
+pub struct FileDelete {
+    msg: String,
     input: String,
+    files: Vec<PathBuf>,
 }
 
-impl<'a> Default for FileDelete<'a> {
+impl FileDelete {
+    /// The files to delete, asks about how many there are if there's more than one
+    pub fn files(mut self, files: Vec<PathBuf>) -> Self {
+        if files.len() > 1 {
+            self.msg = format!(" Are you sure you want to delete {} files [y/n]: ", files.len());
+        }
+        self.files = files;
+        self
+    }
+}
+
+impl Default for FileDelete {
     fn default() -> Self {
         Self {
-            msg: " Are you sure you want to delete this [y/n]: ",
+            msg: " Are you sure you want to delete this [y/n]: ".to_string(),
             input: String::with_capacity(1),
+            files: Vec::new(),
         }
     }
 }
 
-impl <'a> Input for FileDelete<'a> {
+impl Input for FileDelete {
     fn add_to_input(&mut self, ch: char, _: &mut App) {
         if self.input.is_empty() {
             self.input.push(ch);
@@ -34,64 +48,41 @@ impl <'a> Input for FileDelete<'a> {
     }
 
     fn on_enter(&mut self, app: &mut App) {
-        // get the currently selected file
-        let selected_file = app.selected_file().expect("Error getting selected file");
-        // check to see if the user actually wants to delete the file
-        match self.input.to_lowercase().as_str() {
-            "y" => {
-                match selected_file.ftype {
-                    FileType::Directory => match fs::remove_dir_all(&selected_file.path()) {
-                        Ok(_) => {},
-                        Err(e) => match e.kind() {
-                            ErrorKind::PermissionDenied => { 
-                                app.err("Permission Denied")
-                            },
-                            _ => { 
-                                app.err("Unexpected Error")
-                            },
-                        }
-                    },
-                    _ => match fs::remove_file(&selected_file.path()) {
-                        Ok(_) => {},
-                        Err(e) => match e.kind() {
-                            ErrorKind::PermissionDenied => { 
-                                app.err("Permission Denied")
-                            },
-                            _ => app.err("Unexpected Error"),
-                        } 
-                    },
-                };
-            },
-            _ => {
-                app.to_normal_mode()
-            }
-        }
-        
-        app.wd.update().unwrap();
-        app.displayed_files.remove(app.flist_state.selected().unwrap());
+        app.to_normal_mode();
 
-        if app.displayed_files.is_empty() {
-            app.reset_displayed_files()
+        // check to see if the user actually wants to delete the files
+        if self.input.to_lowercase() != "y" {
+            return
         }
 
-        if let Some(selected_idx) = app.flist_state.selected() {
-
-            app.new_list_state();
+        let mut error = None;
+        for path in self.files.drain(..) {
+            // Symlinks to directories are removed, not what they point to
+            let is_dir = path.symlink_metadata().map(|m| m.is_dir()).unwrap_or(false);
+            let result = match is_dir {
+                true => fs::remove_dir_all(&path),
+                false => fs::remove_file(&path),
+            };
 
-            // Determine which file should be highlighted after deletion
-            if selected_idx == 0 {
-            } else if selected_idx == app.displayed_files.len() {
-                app.flist_state.select(Some(selected_idx - 1));
-            } else {
-                app.flist_state.select(Some(selected_idx))
+            match result {
+                Ok(_) => { app.selection.remove(&path); },
+                Err(e) => match e.kind() {
+                    ErrorKind::PermissionDenied => error = Some("Permission Denied"),
+                    _ => error = Some("Unexpected Error"),
+                }
             }
+        }
 
-            app.to_normal_mode();
+        // Keeps the cursor where it was
+        app.reload();
+
+        if let Some(e) = error {
+            app.err(e)
         }
     }
 
-    fn msg(&self) -> &'a str {
-        self.msg
+    fn msg(&self) -> &str {
+        &self.msg
     }
 
     fn input(&self) -> &str {
@@ -0,0 +1,75 @@
+use super::{App, Input};
+
+pub struct GlobSelect {
+    msg: String,
+    input: String,
+    // Unselects the matching files instead
+    unselect: bool,
+}
+
+impl GlobSelect {
+    pub fn unselect(mut self) -> Self {
+        self.unselect = true;
+        self.update_msg(0);
+        self
+    }
+
+    /// Shows how many files match the pattern so far
+    fn update_msg(&mut self, count: usize) {
+        let what = if self.unselect { "Unselect" } else { "Select" };
+        self.msg = format!(" {} ({} matching): ", what, count);
+    }
+}
+
+impl Default for GlobSelect {
+    fn default() -> Self {
+        let mut glob = Self {
+            msg: String::new(),
+            input: String::with_capacity(15),
+            unselect: false,
+        };
+        glob.update_msg(0);
+        glob
+    }
+}
+
+impl Input for GlobSelect {
+    fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    fn input(&self) -> &str {
+        &self.input
+    }
+
+    fn on_enter(&mut self, app: &mut App) {
+        for path in app.glob_matches(&self.input) {
+            if self.unselect {
+                app.selection.remove(&path);
+            } else {
+                app.selection.insert(path);
+            }
+        }
+
+        app.to_normal_mode()
+    }
+
+    fn add_to_input(&mut self, ch: char, app: &mut App) {
+        self.input.push(ch);
+        self.update_msg(app.glob_matches(&self.input).len());
+    }
+
+    fn del(&mut self, app: &mut App) {
+        if !self.input.is_empty() {
+            self.input.pop();
+            self.update_msg(app.glob_matches(&self.input).len());
+        } else {
+            app.to_normal_mode()
+        }
+    }
+
+    fn clear(&mut self) {
+        self.input.clear();
+        self.update_msg(0);
+    }
+}
@@ -1,5 +1,7 @@
 use super::App;
 
+use crossterm::event::KeyCode;
+
 pub trait Input {
     /// Returns the message asking for user input
     fn msg(&self) -> &str;
@@ -19,6 +21,18 @@ pub trait Input {
     /// What to do on press of the Enter key
     fn on_enter(&mut self, app: &mut App);
 
+    /// Candidates shown above the input as the user types,
+    /// as pairs of the candidate and a description
+    fn completions(&self, _app: &App) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// How to handle the tab key being pressed
+    fn complete(&mut self, _app: &mut App) {}
+
+    /// How to handle keys that aren't text, like the arrow keys
+    fn on_key(&mut self, _key: KeyCode, _app: &mut App) {}
+
     /// Outputs the message concatenated with the provided user input thus far
     fn output(&self) -> String {
         format!("{}{}", self.msg(), self.input())
@@ -4,6 +4,10 @@ pub mod delete_file;
 pub mod create_file;
 pub mod rename_file;
 pub mod copy_file;
+pub mod command;
+pub mod glob_select;
+pub mod chmod;
+pub mod chown;
 
 pub use input::Input;
 pub use search::Search;
@@ -11,6 +15,10 @@ pub use delete_file::FileDelete;
 pub use create_file::FileCreate;
 pub use rename_file::FileRename;
 pub use copy_file::FileCopy;
+pub use command::CommandLine;
+pub use glob_select::GlobSelect;
+pub use chmod::Chmod;
+pub use chown::FileChown;
 
 pub use super::App;
 pub use super::InputMode;
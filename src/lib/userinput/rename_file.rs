@@ -35,12 +35,12 @@ impl<'a> Input for FileRename<'a> {
 
         match fs::rename(app.selected_file().unwrap().path(), &new_file){
             Ok(_) => { 
-                app.select_file(&new_file);
+                app.show_file(&new_file);
                 app.to_normal_mode();
             },
             Err(e) => match e.kind() {
                 ErrorKind::AlreadyExists => app.err("Already Exists"),
-                _ => { return app.err(e.to_string().as_str()); }
+                _ => app.err(e.to_string().as_str()),
             }
         }
     }
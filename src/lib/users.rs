@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// User and group names by id, read once from `/etc/passwd` and
+/// `/etc/group` so listing files never has to look them up again
+#[derive(Debug, Default)]
+pub struct Users {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Users {
+    /// Reads the names on this system, ones that can't be read are left out
+    pub fn load() -> Self {
+        Self::from_files(Path::new("/etc/passwd"), Path::new("/etc/group"))
+    }
+
+    /// Reads the names from other passwd and group files
+    pub fn from_files(passwd: &Path, group: &Path) -> Self {
+        let read = |path: &Path| std::fs::read_to_string(path).map(|s| parse(&s)).unwrap_or_default();
+        Self { users: read(passwd), groups: read(group) }
+    }
+
+    /// The name of the user, or the uid when it doesn't have one
+    pub fn user(&self, uid: u32) -> String {
+        self.users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())
+    }
+
+    /// The name of the group, or the gid when it doesn't have one
+    pub fn group(&self, gid: u32) -> String {
+        self.groups.get(&gid).cloned().unwrap_or_else(|| gid.to_string())
+    }
+
+    /// The uid of the user with the name, which can also be a uid
+    pub fn uid(&self, name: &str) -> Option<u32> {
+        lookup(&self.users, name)
+    }
+
+    /// The gid of the group with the name, which can also be a gid
+    pub fn gid(&self, name: &str) -> Option<u32> {
+        lookup(&self.groups, name)
+    }
+
+    /// Every user's name and uid, sorted by name
+    pub fn users(&self) -> Vec<(&str, u32)> {
+        sorted(&self.users)
+    }
+
+    /// Every group's name and gid, sorted by name
+    pub fn groups(&self) -> Vec<(&str, u32)> {
+        sorted(&self.groups)
+    }
+}
+
+fn lookup(names: &HashMap<u32, String>, name: &str) -> Option<u32> {
+    names.iter()
+        .find(|(_, n)| *n == name)
+        .map(|(id, _)| *id)
+        .or_else(|| name.parse().ok())
+}
+
+fn sorted(names: &HashMap<u32, String>) -> Vec<(&str, u32)> {
+    let mut names = names.iter().map(|(id, name)| (name.as_str(), *id)).collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// Who fm is running as, which decides what can be done to files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub uid: u32,
+    pub gid: u32,
+    /// The supplementary groups
+    pub groups: Vec<u32>,
+}
+
+impl Identity {
+    /// The effective user and groups of this process
+    pub fn current() -> Self {
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+
+        // Asking for 0 groups gives how many there are
+        let count = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+        let mut groups = vec![0; count.max(0) as usize];
+        let count = unsafe { libc::getgroups(count, groups.as_mut_ptr()) };
+        groups.truncate(count.max(0) as usize);
+
+        Self { uid, gid, groups }
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.uid == 0
+    }
+
+    /// Returns true if gid is the primary or a supplementary group
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.gid == gid || self.groups.contains(&gid)
+    }
+}
+
+/// Names by id from the lines of a passwd or group file,
+/// which both start with `name:password:id:`
+fn parse(contents: &str) -> HashMap<u32, String> {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Identity, Users};
+
+    #[test]
+    fn test_names() {
+        let dir = std::env::temp_dir().join(format!("fm-users-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("passwd"), "root:x:0:0:root:/root:/bin/sh\n# comment\nbroken\nme:x:1000:1000::/home/me:/bin/sh\n").unwrap();
+        std::fs::write(dir.join("group"), "wheel:x:10:root,me\n").unwrap();
+
+        let users = Users::from_files(&dir.join("passwd"), &dir.join("group"));
+        assert_eq!(users.user(1000), "me");
+        assert_eq!(users.user(0), "root");
+        assert_eq!(users.user(42), "42");
+        assert_eq!(users.group(10), "wheel");
+        assert_eq!(users.uid("me"), Some(1000));
+        assert_eq!(users.uid("1234"), Some(1234));
+        assert_eq!(users.gid("nobody"), None);
+        assert_eq!(users.users(), vec![("me", 1000), ("root", 0)]);
+
+        // Missing files leave ids as they are
+        let users = Users::from_files(&dir.join("none"), &dir.join("none"));
+        assert_eq!(users.group(10), "10");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_identity() {
+        let me = Identity::current();
+        assert_eq!(me.uid, unsafe { libc::geteuid() });
+        assert!(me.in_group(me.gid));
+    }
+}
@@ -0,0 +1,111 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use inotify::{Inotify, WatchDescriptor, WatchMask, Watches};
+
+// How long the directory has to be quiet before a change is reported
+const QUIET: Duration = Duration::from_millis(100);
+// Longest a change waits to be reported while the directory stays busy
+const MAX_DELAY: Duration = Duration::from_millis(500);
+
+/// Watches directories with inotify and reports changes
+/// once they have settled down
+pub struct Watcher {
+    watches: Watches,
+    watching: Vec<(PathBuf, WatchDescriptor)>,
+}
+
+impl Watcher {
+    /// Starts watching in another thread, on_change is called after every
+    /// burst of changes and returns false once nobody is listening anymore
+    pub fn new<F>(on_change: F) -> anyhow::Result<Self>
+    where
+        F: Fn() -> bool + Send + 'static,
+    {
+        let mut inotify = Inotify::init()?;
+        let watches = inotify.watches();
+
+        std::thread::spawn(move || {
+            let mut buf = [0; 4096];
+
+            // Wait for something to change
+            while inotify.read_events_blocking(&mut buf).is_ok() {
+                // Then wait for it to settle down
+                let start = Instant::now();
+                while start.elapsed() < MAX_DELAY {
+                    std::thread::sleep(QUIET);
+                    let quiet = match inotify.read_events(&mut buf) {
+                        Ok(events) => events.count() == 0,
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => true,
+                        Err(_) => return,
+                    };
+
+                    if quiet {
+                        break
+                    }
+                }
+
+                if !on_change() {
+                    return
+                }
+            }
+        });
+
+        Ok(Self { watches, watching: vec![] })
+    }
+
+    /// Watches exactly the directories given
+    pub fn watch(&mut self, dirs: &[PathBuf]) {
+        let (keep, stop): (Vec<_>, Vec<_>) = std::mem::take(&mut self.watching)
+            .into_iter()
+            .partition(|(dir, _)| dirs.contains(dir));
+
+        // Removing fails if the directory is already gone
+        for (_, wd) in stop {
+            let _ = self.watches.remove(wd);
+        }
+        self.watching = keep;
+
+        let mask = WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVE
+            | WatchMask::MODIFY | WatchMask::ATTRIB
+            | WatchMask::DELETE_SELF | WatchMask::MOVE_SELF;
+
+        for dir in dirs {
+            if self.watching.iter().any(|(d, _)| d == dir) {
+                continue
+            }
+
+            if let Ok(wd) = self.watches.add(dir, mask) {
+                self.watching.push((dir.clone(), wd))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Watcher;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reports_changes() {
+        let dir = std::env::temp_dir().join(format!("fm-watch-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = Watcher::new(move || tx.send(()).is_ok()).unwrap();
+        watcher.watch(std::slice::from_ref(&dir));
+
+        // A burst of changes is reported once
+        for i in 0..5 {
+            std::fs::write(dir.join(format!("file{}", i)), "").unwrap();
+        }
+
+        assert!(rx.recv_timeout(Duration::from_secs(2)).is_ok());
+        assert!(rx.recv_timeout(Duration::from_millis(300)).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
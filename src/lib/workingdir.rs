@@ -2,6 +2,10 @@ use std::path::PathBuf;
 use std::path::Path;
 
 use super::file::*;
+use super::filter::{Filter, GitignoreCache};
+use super::sort::Sort;
+
+use anyhow::Context;
 
 /// Struct containing information about the Current working directory
 #[derive(Clone, Debug)]
@@ -9,22 +13,31 @@ pub struct WorkingDir {
     cwd: PathBuf,
     files: Vec<File>,
     len: usize,
+    filter: Filter,
+    sort: Sort,
+    gitignores: GitignoreCache,
 }
 
 impl WorkingDir {
     /// Creates a new instance of WorkingDir. This can fail because it calls
-    /// std::env::current_dir()
-    pub fn new(dir: Option<&Path>) -> anyhow::Result<Self> {
+    /// std::env::current_dir() and reads the directory
+    pub fn new(dir: Option<&Path>, filter: Filter, sort: Sort) -> anyhow::Result<Self> {
         let cwd = match dir {
             Some(dir) => PathBuf::from(dir),
-            None => std::env::current_dir()?,
+            None => std::env::current_dir().context("Can't read the current directory")?,
 
         };
 
-        let mut files = Self::get_files(&cwd).unwrap();
-        files.sort();
-        let len = files.len();
-        Ok(Self { cwd, files, len })
+        let wd = Self { cwd: PathBuf::new(), files: Vec::new(), len: 0, filter, sort, gitignores: GitignoreCache::default() };
+        wd.at(&cwd)
+    }
+
+    /// A working dir in dir that lists files the same way as self and
+    /// shares the `.gitignore` files it has read
+    pub fn at(&self, dir: &Path) -> anyhow::Result<Self> {
+        let mut wd = Self { cwd: dir.to_owned(), ..self.clone() };
+        wd.update().with_context(|| format!("Can't read {}", dir.display()))?;
+        Ok(wd)
     }
 
     /// Moves the cwd to self.cwd + path
@@ -54,6 +67,31 @@ impl WorkingDir {
         Ok(())
     }
 
+    /// Which files are listed
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// Changes which files are listed, they're reread on the next update
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    /// How files are sorted
+    pub fn sort(&self) -> Sort {
+        self.sort
+    }
+
+    /// Changes how files are sorted, they're resorted on the next update
+    pub fn set_sort(&mut self, sort: Sort) {
+        self.sort = sort;
+    }
+
+    /// Forgets the `.gitignore` files read so far, for when they may have changed
+    pub fn forget_gitignores(&self) {
+        self.gitignores.forget();
+    }
+
     /// Returns the current working directory
     pub fn cwd(&self) -> &PathBuf {
         &self.cwd
@@ -76,12 +114,13 @@ impl WorkingDir {
     }
 
     /// Uses std::fs::read_dir() to read the contents of the directory and then uses 
-    /// try_from to convert a DirEntry into a File struct
-    pub fn get_files(path: &Path) -> std::io::Result<Vec<File>> {
+    /// try_from to convert a DirEntry into a File struct, filtered and sorted like the cwd
+    pub fn get_files(&self, path: &Path) -> std::io::Result<Vec<File>> {
         match std::fs::read_dir(path) {
             Ok(iter) => { 
                 let mut files = iter.map(|d| File::from(d.unwrap())).collect::<Vec<_>>();
-                files.sort();
+                self.filter.apply(path, &mut files, &self.gitignores);
+                self.sort.sort(&mut files);
                 Ok(files)
             },
             Err(e) => Err(e),
@@ -91,7 +130,7 @@ impl WorkingDir {
     /// Gets the files from the current working directory
     /// and updates the files to be held internally by App
     pub fn update(&mut self) -> anyhow::Result<()> {
-        self.files = Self::get_files(&self.cwd)?;
+        self.files = self.get_files(&self.cwd)?;
         self.len = self.files.len();
         Ok(())
     }
@@ -100,6 +139,8 @@ impl WorkingDir {
 #[cfg(test)]
 mod tests {
     use super::WorkingDir;
+    use crate::filter::{Filter, GitignoreCache};
+    use crate::sort::Sort;
     use std::path::PathBuf;
 
     fn testing_working_dir() -> WorkingDir {
@@ -107,9 +148,19 @@ mod tests {
             cwd: PathBuf::from("Test"),
             files: vec![],
             len: 0,
+            filter: Filter::default(),
+            sort: Sort::default(),
+            gitignores: GitignoreCache::default(),
         }
     }
 
+    #[test]
+    fn test_empty() {
+        let working_dir = testing_working_dir();
+        assert!(working_dir.is_empty());
+        assert_eq!(working_dir.len(), 0);
+    }
+
     // #[test]
     // fn set_test() {
     //     let mut working_dir = testing_working_dir();